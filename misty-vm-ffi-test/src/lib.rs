@@ -0,0 +1,72 @@
+//! A counter app exported through the ffi of misty-vm, driven by `tests/harness.c`.
+
+use std::{convert::Infallible, sync::Once};
+
+use misty_vm::{
+    client::{AsReadonlyMistyClientHandle, MistyClient},
+    codecs::MistyJsonCodec,
+    controllers::MistyControllerContext,
+    ffi::register_app,
+    misty_states,
+    registry::MistyControllerRegistry,
+    states::{MistyStateManager, MistyStateTrait},
+    views::MistyViewModelManager,
+    MistyView,
+};
+use misty_vm_test::fixtures::{new_client, CounterState};
+use serde::Serialize;
+
+#[derive(Debug, Default, Clone, MistyView)]
+#[misty(patch_derive(Serialize))]
+struct RootViewModelState {
+    pub count: i32,
+}
+
+fn controller_add(ctx: MistyControllerContext, arg: i32) -> Result<i32, String> {
+    if arg < 0 {
+        return Err("negative".to_string());
+    }
+    let count = CounterState::update(&ctx, |state| {
+        state.count += arg;
+        state.count
+    });
+    Ok(count)
+}
+
+fn controller_schedule_add(ctx: MistyControllerContext, arg: i32) -> Result<(), Infallible> {
+    ctx.handle().readonly_handle().schedule(move |handle| {
+        CounterState::update(handle, |state| {
+            state.count += arg;
+        });
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn counter_view_model(state: &CounterState, root: &mut RootViewModelState) {
+    root.count = state.count;
+}
+
+fn build_client() -> MistyClient<RootViewModelState> {
+    let view_manager = MistyViewModelManager::builder()
+        .register(counter_view_model)
+        .build();
+    let state_manager = MistyStateManager::new(misty_states!(CounterState));
+    let client = new_client(view_manager, state_manager);
+    client.set_controller_registry(
+        MistyControllerRegistry::builder(MistyJsonCodec)
+            .register("add", controller_add)
+            .register("schedule_add", controller_schedule_add)
+            .build(),
+    );
+    client
+}
+
+/// Registers the app as `counter`. Called by the harness before creating clients.
+#[no_mangle]
+pub extern "C" fn counter_app_register() {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        register_app("counter", MistyJsonCodec, build_client);
+    });
+}
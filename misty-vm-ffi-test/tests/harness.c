@@ -0,0 +1,77 @@
+#include <stdio.h>
+#include <string.h>
+
+#include "misty_vm.h"
+
+void counter_app_register(void);
+
+static int failures = 0;
+
+#define CHECK(cond)                                                  \
+  do {                                                               \
+    if (!(cond)) {                                                   \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,         \
+              __LINE__, #cond);                                      \
+      failures++;                                                    \
+    }                                                                \
+  } while (0)
+
+static void on_schedule(void *user_data) { (*(int *)user_data)++; }
+
+/* Checks the buffer is a json text containing `expected`, and frees it. */
+static void expect_buffer(MistyFfiBuffer buf, const char *expected) {
+  char text[1024] = {0};
+  size_t len = buf.len < sizeof(text) - 1 ? buf.len : sizeof(text) - 1;
+  memcpy(text, buf.ptr, len);
+  if (strstr(text, expected) == NULL) {
+    fprintf(stderr, "expect %s in %s\n", expected, text);
+    failures++;
+  }
+  misty_buffer_free(buf);
+}
+
+static MistyFfiBuffer call(MistyFfiClient *client, const char *controller,
+                           const char *arg) {
+  return misty_client_call(client, controller, (const uint8_t *)arg,
+                           strlen(arg));
+}
+
+int main(void) {
+  counter_app_register();
+  CHECK(misty_client_create("missing", NULL, NULL) == NULL);
+
+  int scheduled = 0;
+  MistyFfiClient *client = misty_client_create("counter", on_schedule, &scheduled);
+  CHECK(client != NULL);
+
+  expect_buffer(call(client, "add", "2"), "\"changed_view\":{\"count\":2}");
+  expect_buffer(call(client, "add", "0"), "\"value\":2");
+  expect_buffer(call(client, "add", "-1"),
+                "\"Err\":{\"kind\":\"Failed\",\"message\":\"negative\"}");
+  expect_buffer(call(client, "sub", "1"), "\"kind\":\"UnknownController\"");
+  expect_buffer(call(client, "add", "\"2\""), "\"kind\":\"Codec\"");
+
+  MistyFfiBuffer buf = misty_client_poll(client);
+  CHECK(buf.len == 0);
+  misty_buffer_free(buf);
+
+  expect_buffer(call(client, "schedule_add", "3"), "\"changed_view\":null");
+  CHECK(scheduled == 1);
+  expect_buffer(misty_client_poll(client), "\"changed_view\":{\"count\":5}");
+  buf = misty_client_poll(client);
+  CHECK(buf.len == 0);
+  misty_buffer_free(buf);
+
+  expect_buffer(call(client, "schedule_add", "1"), "\"value\":null");
+  CHECK(scheduled == 2);
+  expect_buffer(misty_client_flush_scheduled_tasks(client),
+                "\"changed_view\":{\"count\":6}");
+
+  misty_client_destroy(client);
+
+  if (failures > 0) {
+    fprintf(stderr, "%d checks failed\n", failures);
+    return 1;
+  }
+  return 0;
+}
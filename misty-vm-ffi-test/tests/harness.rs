@@ -0,0 +1,37 @@
+use std::{path::PathBuf, process::Command};
+
+/// Directory of the cdylib, which is built next to the test executable.
+fn lib_dir() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    exe.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn test_c_harness() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let lib_dir = lib_dir();
+    let harness = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("misty_vm_harness");
+
+    let status = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(manifest_dir.join("tests/harness.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("../misty-vm/include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lmisty_vm_ffi_test")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-o")
+        .arg(&harness)
+        .status()
+        .unwrap();
+    assert!(status.success(), "fail to compile the harness");
+
+    let output = Command::new(&harness).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
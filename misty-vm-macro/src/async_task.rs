@@ -1,10 +1,39 @@
 use quote::quote;
-use syn::{parse2, DeriveInput};
+use syn::{parse2, DeriveInput, LitInt};
+
+fn parse_cancel_grace_period_ms(input: &DeriveInput) -> syn::Result<Option<LitInt>> {
+    let mut grace_period_ms = None;
+    for attr in input.attrs.iter() {
+        if !attr.path().is_ident("misty") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("cancel_grace_period_ms") {
+                grace_period_ms = Some(meta.value()?.parse::<LitInt>()?);
+                return Ok(());
+            }
+            Err(meta.error("unsupported misty async task attribute"))
+        })?;
+    }
+    Ok(grace_period_ms)
+}
 
 pub fn parse_misty_async_task_derive(input: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
     let input = parse2::<DeriveInput>(input).unwrap();
     let name = &input.ident;
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let grace_period_ms = match parse_cancel_grace_period_ms(&input) {
+        Ok(grace_period_ms) => grace_period_ms,
+        Err(err) => return err.to_compile_error(),
+    };
+
+    let cancel_grace_period = grace_period_ms.map(|ms| {
+        quote! {
+            fn cancel_grace_period() -> Option<std::time::Duration> {
+                Some(std::time::Duration::from_millis(#ms))
+            }
+        }
+    });
 
     let output: proc_macro2::TokenStream = quote! {
         const _: () = {
@@ -14,7 +43,7 @@ pub fn parse_misty_async_task_derive(input: proc_macro2::TokenStream) -> proc_ma
             use std::sync::RwLock;
 
             impl #impl_generics MistyAsyncTaskTrait for #name #ty_generics #where_clause {
-
+                #cancel_grace_period
             }
         };
     };
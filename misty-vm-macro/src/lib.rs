@@ -1,10 +1,12 @@
 use async_task::parse_misty_async_task_derive;
 use service::parse_misty_service;
 use state::{parse_misty_state_derive, parse_misty_states};
+use view::parse_misty_view_derive;
 
 mod async_task;
 mod service;
 mod state;
+mod view;
 
 extern crate proc_macro;
 
@@ -15,7 +17,7 @@ pub fn misty_states(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     proc_macro::TokenStream::from(output)
 }
 
-#[proc_macro_derive(MistyAsyncTask)]
+#[proc_macro_derive(MistyAsyncTask, attributes(misty))]
 pub fn misty_async_task_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = proc_macro2::TokenStream::from(input);
     let output = parse_misty_async_task_derive(input);
@@ -29,9 +31,16 @@ pub fn misty_service(input: proc_macro::TokenStream) -> proc_macro::TokenStream
     proc_macro::TokenStream::from(output)
 }
 
-#[proc_macro_derive(MistyState)]
+#[proc_macro_derive(MistyState, attributes(misty))]
 pub fn misty_state_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = proc_macro2::TokenStream::from(input);
     let output = parse_misty_state_derive(input);
     proc_macro::TokenStream::from(output)
 }
+
+#[proc_macro_derive(MistyView, attributes(misty))]
+pub fn misty_view_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = proc_macro2::TokenStream::from(input);
+    let output = parse_misty_view_derive(input);
+    proc_macro::TokenStream::from(output)
+}
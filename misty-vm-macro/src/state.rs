@@ -3,7 +3,7 @@ use syn::{
     parse::{Parse, ParseStream},
     parse2,
     punctuated::Punctuated,
-    DeriveInput,
+    DeriveInput, LitStr,
 };
 
 struct StatesStruct {
@@ -45,19 +45,89 @@ pub fn parse_misty_states(input: proc_macro2::TokenStream) -> proc_macro2::Token
     output
 }
 
+#[derive(Default)]
+struct StateAttrs {
+    persist: Option<Option<LitStr>>,
+    undoable: bool,
+    computed: bool,
+}
+
+fn parse_state_attrs(input: &DeriveInput) -> syn::Result<StateAttrs> {
+    let mut attrs = StateAttrs::default();
+    for attr in input.attrs.iter() {
+        if !attr.path().is_ident("misty") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("persist") {
+                let key = if meta.input.peek(syn::Token![=]) {
+                    Some(meta.value()?.parse::<LitStr>()?)
+                } else {
+                    None
+                };
+                attrs.persist = Some(key);
+                return Ok(());
+            }
+            if meta.path.is_ident("undoable") {
+                attrs.undoable = true;
+                return Ok(());
+            }
+            if meta.path.is_ident("computed") {
+                attrs.computed = true;
+                return Ok(());
+            }
+            Err(meta.error("unsupported misty state attribute"))
+        })?;
+    }
+    Ok(attrs)
+}
+
 pub fn parse_misty_state_derive(input: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
     let input = parse2::<DeriveInput>(input).unwrap();
     let name = &input.ident;
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let attrs = match parse_state_attrs(&input) {
+        Ok(attrs) => attrs,
+        Err(err) => return err.to_compile_error(),
+    };
+
+    let persistence = attrs.persist.map(|key| {
+        let key = key.unwrap_or_else(|| LitStr::new(&name.to_string(), name.span()));
+        quote! {
+            fn persistence() -> Option<MistyStatePersistence> {
+                Some(MistyStatePersistence::new::<Self>(#key))
+            }
+        }
+    });
+
+    let undoable = attrs.undoable.then(|| {
+        quote! {
+            fn undoable() -> bool {
+                true
+            }
+        }
+    });
+
+    let computed = attrs.computed.then(|| {
+        quote! {
+            fn computed() -> Option<MistyComputedState> {
+                Some(MistyComputedState::new::<Self>())
+            }
+        }
+    });
 
     let output: proc_macro2::TokenStream = quote! {
         const _: () = {
             use misty_vm::client::MistyClientId;
-            use misty_vm::states::{MistyStateTrait};
+            use misty_vm::states::{MistyComputedState, MistyStatePersistence, MistyStateTrait};
             use std::collections::HashMap;
             use std::sync::RwLock;
 
-            impl #impl_generics MistyStateTrait for #name #ty_generics #where_clause {}
+            impl #impl_generics MistyStateTrait for #name #ty_generics #where_clause {
+                #persistence
+                #undoable
+                #computed
+            }
         };
     };
 
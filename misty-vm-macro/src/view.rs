@@ -0,0 +1,244 @@
+use proc_macro2::Ident;
+use quote::{format_ident, quote};
+use syn::{
+    parse2, punctuated::Punctuated, Data, DeriveInput, Field, Fields, GenericArgument, Path,
+    PathArguments, Type,
+};
+
+#[derive(Default)]
+struct ViewAttrs {
+    patch_derives: Vec<Path>,
+}
+
+enum FieldKind {
+    Value,
+    View,
+    List { key: Ident, item: Box<Type> },
+}
+
+fn parse_view_attrs(input: &DeriveInput) -> syn::Result<ViewAttrs> {
+    let mut attrs = ViewAttrs::default();
+    for attr in input.attrs.iter() {
+        if !attr.path().is_ident("misty") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("patch_derive") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                let paths = Punctuated::<Path, syn::Token![,]>::parse_terminated(&content)?;
+                attrs.patch_derives.extend(paths);
+                return Ok(());
+            }
+            Err(meta.error("unsupported misty view attribute"))
+        })?;
+    }
+    Ok(attrs)
+}
+
+fn vec_item_type(ty: &Type) -> Option<Type> {
+    let Type::Path(ty) = ty else {
+        return None;
+    };
+    let segment = ty.path.segments.last()?;
+    if segment.ident != "Vec" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first() {
+        Some(GenericArgument::Type(item)) => Some(item.clone()),
+        _ => None,
+    }
+}
+
+fn parse_field_kind(field: &Field) -> syn::Result<FieldKind> {
+    let mut kind = FieldKind::Value;
+    for attr in field.attrs.iter() {
+        if !attr.path().is_ident("misty") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("view") {
+                kind = FieldKind::View;
+                return Ok(());
+            }
+            if meta.path.is_ident("list") {
+                let mut key: Option<Ident> = None;
+                meta.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("key") {
+                        key = Some(meta.value()?.parse()?);
+                        return Ok(());
+                    }
+                    Err(meta.error("unsupported misty list attribute"))
+                })?;
+                let key = key.ok_or_else(|| meta.error("missing list key"))?;
+                let item = vec_item_type(&field.ty)
+                    .ok_or_else(|| meta.error("keyed list fields must be a Vec"))?;
+                kind = FieldKind::List {
+                    key,
+                    item: Box::new(item),
+                };
+                return Ok(());
+            }
+            Err(meta.error("unsupported misty view field attribute"))
+        })?;
+    }
+    Ok(kind)
+}
+
+pub fn parse_misty_view_derive(input: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let input = parse2::<DeriveInput>(input).unwrap();
+    match expand_misty_view_derive(&input) {
+        Ok(output) => output,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn expand_misty_view_derive(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let patch_name = format_ident!("{}Patch", name);
+    let attrs = parse_view_attrs(input)?;
+    let patch_derives = attrs.patch_derives;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "MistyView only supports structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "MistyView only supports structs",
+            ))
+        }
+    };
+
+    let mut patch_fields = Vec::new();
+    let mut diff_fields = Vec::new();
+    let mut apply_fields = Vec::new();
+    let mut copy_fields = Vec::new();
+    for field in fields.iter() {
+        let field_name = field.ident.as_ref().unwrap();
+        let field_vis = &field.vis;
+        let field_type = &field.ty;
+        match parse_field_kind(field)? {
+            FieldKind::Value => {
+                patch_fields.push(quote! {
+                    #field_vis #field_name: Option<#field_type>
+                });
+                diff_fields.push(quote! {
+                    #field_name: if prev.#field_name != next.#field_name {
+                        changed = true;
+                        Some(next.#field_name.clone())
+                    } else {
+                        None
+                    }
+                });
+                apply_fields.push(quote! {
+                    if let Some(value) = patch.#field_name {
+                        self.#field_name = value;
+                    }
+                });
+                copy_fields.push(quote! {
+                    if patch.#field_name.is_some() {
+                        self.#field_name.clone_from(&next.#field_name);
+                    }
+                });
+            }
+            FieldKind::View => {
+                patch_fields.push(quote! {
+                    #field_vis #field_name: Option<<#field_type as misty_vm::views::MistyViewTrait>::Patch>
+                });
+                diff_fields.push(quote! {
+                    #field_name: {
+                        let patch = <#field_type as MistyViewTrait>::diff(&prev.#field_name, &next.#field_name);
+                        changed |= patch.is_some();
+                        patch
+                    }
+                });
+                apply_fields.push(quote! {
+                    if let Some(value) = patch.#field_name {
+                        self.#field_name.apply_patch(value);
+                    }
+                });
+                copy_fields.push(quote! {
+                    if let Some(patch) = patch.#field_name.as_ref() {
+                        self.#field_name.copy_changed(&next.#field_name, patch);
+                    }
+                });
+            }
+            FieldKind::List { key, item } => {
+                patch_fields.push(quote! {
+                    #field_vis #field_name: Option<Vec<misty_vm::views::ListDiffOp<#item>>>
+                });
+                diff_fields.push(quote! {
+                    #field_name: {
+                        let ops = misty_vm::views::diff_keyed_list(
+                            &prev.#field_name,
+                            &next.#field_name,
+                            |item| item.#key.clone(),
+                        );
+                        if ops.is_empty() {
+                            None
+                        } else {
+                            changed = true;
+                            Some(ops)
+                        }
+                    }
+                });
+                apply_fields.push(quote! {
+                    if let Some(ops) = patch.#field_name {
+                        misty_vm::views::apply_list_diff(&mut self.#field_name, ops);
+                    }
+                });
+                copy_fields.push(quote! {
+                    if patch.#field_name.is_some() {
+                        self.#field_name.clone_from(&next.#field_name);
+                    }
+                });
+            }
+        }
+    }
+
+    let output = quote! {
+        #[derive(Clone, Default, #(#patch_derives),*)]
+        #vis struct #patch_name #generics #where_clause {
+            #(#patch_fields,)*
+        }
+
+        const _: () = {
+            use misty_vm::views::MistyViewTrait;
+
+            impl #impl_generics MistyViewTrait for #name #ty_generics #where_clause {
+                type Patch = #patch_name #ty_generics;
+
+                fn diff(prev: &Self, next: &Self) -> Option<Self::Patch> {
+                    let mut changed = false;
+                    let patch = #patch_name {
+                        #(#diff_fields,)*
+                    };
+                    changed.then_some(patch)
+                }
+
+                fn apply_patch(&mut self, patch: Self::Patch) {
+                    #(#apply_fields)*
+                }
+
+                fn copy_changed(&mut self, next: &Self, patch: &Self::Patch) {
+                    #(#copy_fields)*
+                }
+            }
+        };
+    };
+    Ok(output)
+}
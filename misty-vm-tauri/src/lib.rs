@@ -0,0 +1,153 @@
+//! A Tauri plugin driving a [`SingletonMistyClientPod`].
+//!
+//! The client must be created before the Tauri app is built. [`init`] gives it the controllers,
+//! registered with [`MistyJsonCodec`] since the webview sends and receives JSON. Add
+//! [`misty_call`] to the invoke handler of the app, so the webview can call controllers with
+//! `invoke("misty_call", { name, arg })`. View patches and resource actions of every call
+//! and of scheduled handlers are emitted as [`UPDATE_EVENT`].
+//!
+//! The plugin owns the signal handler of the client, which flushes scheduled handlers on the
+//! main thread. Hosts should not call `on_signal` on the client themselves.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use misty_vm::{
+    client::SingletonMistyClientPod,
+    codecs::{MistyCodec, MistyJsonCodec},
+    registry::MistyControllerRegistryBuilder,
+    resources::ResourceUpdateAction,
+    signals::MistySignal,
+    views::MistyViewTrait,
+    wire::{MistyWireError, MistyWireErrorKind, MistyWireRet, MistyWireValue},
+};
+use serde::Serialize;
+use tauri::{
+    plugin::{Builder, TauriPlugin},
+    AppHandle, Emitter, Manager, Runtime,
+};
+
+pub const UPDATE_EVENT: &str = "misty://update";
+
+/// Payload of [`UPDATE_EVENT`].
+#[derive(Debug, Clone, Serialize)]
+pub struct MistyTauriUpdate<P> {
+    pub changed_view: Option<P>,
+    pub changed_resources: Vec<ResourceUpdateAction>,
+    pub seq: Option<u64>,
+}
+
+type Dispatch =
+    Box<dyn Fn(&str, serde_json::Value) -> Result<serde_json::Value, MistyWireError> + Send + Sync>;
+
+pub struct MistyTauriState {
+    dispatch: Dispatch,
+}
+
+// commands cannot be public in the crate root, which also holds the macros they export
+mod commands {
+    use misty_vm::wire::MistyWireError;
+    use tauri::State;
+
+    use crate::MistyTauriState;
+
+    /// Calls the controller registered as `name`, and returns its value.
+    #[tauri::command]
+    pub fn misty_call(
+        state: State<'_, MistyTauriState>,
+        name: String,
+        arg: serde_json::Value,
+    ) -> Result<serde_json::Value, MistyWireError> {
+        (state.dispatch)(&name, arg)
+    }
+}
+
+pub use commands::misty_call;
+
+fn emit_update<R: Runtime, P: Serialize + Clone>(
+    app: &AppHandle<R>,
+    value: MistyWireValue<P, impl Sized>,
+) {
+    if value.changed_view.is_none() && value.changed_resources.is_empty() {
+        return;
+    }
+    let update = MistyTauriUpdate {
+        changed_view: value.changed_view,
+        changed_resources: value.changed_resources,
+        seq: value.seq,
+    };
+    if let Err(err) = app.emit(UPDATE_EVENT, update) {
+        tracing::error!("fail to emit update: {}", err);
+    }
+}
+
+fn codec_error(err: impl std::fmt::Display) -> MistyWireError {
+    MistyWireError {
+        kind: MistyWireErrorKind::Codec,
+        message: err.to_string(),
+    }
+}
+
+/// Sets the controller registry of `client` to `controllers`. When the app is set up, the
+/// signal handler of `client` is replaced with one that flushes scheduled handlers.
+pub fn init<R, V>(
+    client: &'static SingletonMistyClientPod<V>,
+    controllers: MistyControllerRegistryBuilder<V, MistyJsonCodec>,
+) -> TauriPlugin<R>
+where
+    R: Runtime,
+    V: MistyViewTrait,
+    V::Patch: Serialize + Clone,
+{
+    client.set_controller_registry(controllers.build());
+    Builder::new("misty")
+        .setup(move |app, _api| {
+            let scheduled: Arc<AtomicBool> = Default::default();
+            let handle = app.clone();
+            client.on_signal(move |signal| match signal {
+                MistySignal::Schedule => {
+                    // handlers scheduled before the flush runs are flushed together
+                    if scheduled.swap(true, Ordering::SeqCst) {
+                        return;
+                    }
+                    let scheduled = scheduled.clone();
+                    let app = handle.clone();
+                    let res = handle.run_on_main_thread(move || {
+                        scheduled.store(false, Ordering::SeqCst);
+                        match client.flush_scheduled_tasks() {
+                            Ok(ret) => {
+                                if let Ok(value) = MistyWireRet::<V::Patch, ()>::from(ret).result {
+                                    emit_update(&app, value);
+                                }
+                            }
+                            Err(err) => tracing::error!("fail to flush scheduled tasks: {}", err),
+                        }
+                    });
+                    if let Err(err) = res {
+                        tracing::error!("fail to run on main thread: {}", err);
+                    }
+                }
+            });
+
+            let handle = app.clone();
+            app.manage(MistyTauriState {
+                dispatch: Box::new(move |name, arg| {
+                    let arg = MistyJsonCodec.encode(&arg).map_err(codec_error)?;
+                    let buf = client.call_controller_by_name(name, &arg);
+                    let ret = MistyWireRet::<serde_json::Value, serde_json::Value>::decode(
+                        &MistyJsonCodec,
+                        &buf,
+                    )
+                    .map_err(codec_error)?;
+                    let value = ret.result?;
+                    let ret = value.value.clone();
+                    emit_update(&handle, value);
+                    Ok(ret)
+                }),
+            });
+            Ok(())
+        })
+        .build()
+}
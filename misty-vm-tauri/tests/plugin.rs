@@ -0,0 +1,140 @@
+use std::convert::Infallible;
+
+use misty_vm::{
+    client::SingletonMistyClientPod, controllers::MistyControllerContext, states::MistyStateTrait,
+    MistyView,
+};
+use misty_vm_test::fixtures::CounterState;
+use serde::Serialize;
+
+static CLIENT: SingletonMistyClientPod<RootViewModelState> = SingletonMistyClientPod::new();
+static SCHEDULE_CLIENT: SingletonMistyClientPod<RootViewModelState> =
+    SingletonMistyClientPod::new();
+
+#[derive(Debug, Default, Clone, MistyView)]
+#[misty(patch_derive(Serialize))]
+struct RootViewModelState {
+    pub count: i32,
+}
+
+fn controller_add(ctx: MistyControllerContext, arg: i32) -> Result<i32, Infallible> {
+    let count = CounterState::update(&ctx, |state| {
+        state.count += arg;
+        state.count
+    });
+    Ok(count)
+}
+
+fn counter_view_model(state: &CounterState, root: &mut RootViewModelState) {
+    root.count = state.count;
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+    };
+
+    use misty_vm::{
+        client::SingletonMistyClientPod,
+        codecs::MistyJsonCodec,
+        misty_states,
+        registry::MistyControllerRegistry,
+        services::MistyServiceManager,
+        states::{MistyStateManager, MistyStateTrait},
+        views::MistyViewModelManager,
+    };
+    use misty_vm_test::runtime::DeterministicRuntime;
+    use tauri::{
+        ipc::{CallbackFn, InvokeBody},
+        test::{
+            get_ipc_response, mock_builder, mock_context, noop_assets, MockRuntime, INVOKE_KEY,
+        },
+        webview::InvokeRequest,
+        App, Listener, WebviewWindow, WebviewWindowBuilder,
+    };
+
+    use crate::{
+        controller_add, counter_view_model, CounterState, RootViewModelState, CLIENT,
+        SCHEDULE_CLIENT,
+    };
+
+    type Updates = Arc<Mutex<Vec<String>>>;
+
+    /// Builds a Tauri app driving `client`, and collects the payloads of its update events.
+    fn build_app(
+        client: &'static SingletonMistyClientPod<RootViewModelState>,
+    ) -> (App<MockRuntime>, WebviewWindow<MockRuntime>, Updates) {
+        client.create(
+            MistyViewModelManager::builder()
+                .register(counter_view_model)
+                .build(),
+            MistyStateManager::new(misty_states!(CounterState)),
+            MistyServiceManager::builder().build(),
+            DeterministicRuntime::new().adapter(),
+        );
+        let controllers =
+            MistyControllerRegistry::builder(MistyJsonCodec).register("add", controller_add);
+
+        let app = mock_builder()
+            .plugin(misty_vm_tauri::init(client, controllers))
+            .invoke_handler(tauri::generate_handler![misty_vm_tauri::misty_call])
+            .build(mock_context(noop_assets()))
+            .unwrap();
+        let updates: Updates = Default::default();
+        {
+            let updates = updates.clone();
+            app.listen_any(misty_vm_tauri::UPDATE_EVENT, move |event| {
+                updates.lock().unwrap().push(event.payload().to_string());
+            });
+        }
+        let webview = WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .unwrap();
+        (app, webview, updates)
+    }
+
+    #[test]
+    fn test_misty_call() {
+        let (_app, webview, updates) = build_app(&CLIENT);
+
+        let res = get_ipc_response(
+            &webview,
+            InvokeRequest {
+                cmd: "misty_call".into(),
+                callback: CallbackFn(0),
+                error: CallbackFn(1),
+                // local to the app, so no capability is needed for app commands
+                url: webview.url().unwrap(),
+                body: InvokeBody::from(serde_json::json!({ "name": "add", "arg": 2 })),
+                headers: Default::default(),
+                invoke_key: INVOKE_KEY.to_string(),
+            },
+        );
+        assert_eq!(res.unwrap().deserialize::<i32>().unwrap(), 2);
+        assert_eq!(
+            updates.lock().unwrap().clone(),
+            vec![r#"{"changed_view":{"count":2},"changed_resources":[],"seq":1}"#.to_string()]
+        );
+    }
+
+    #[test]
+    fn test_flush_on_schedule() {
+        let (_app, _webview, updates) = build_app(&SCHEDULE_CLIENT);
+
+        // scheduled outside any controller, so the plugin flushes on the main thread, which
+        // the mock runtime runs at once on the thread that built the app
+        let pod = SCHEDULE_CLIENT.accessor().get().unwrap();
+        pod.handle().schedule(|handle| {
+            CounterState::update(handle, |state| {
+                state.count += 3;
+            });
+            Ok::<(), Infallible>(())
+        });
+        assert_eq!(
+            updates.lock().unwrap().clone(),
+            vec![r#"{"changed_view":{"count":3},"changed_resources":[],"seq":1}"#.to_string()]
+        );
+    }
+}
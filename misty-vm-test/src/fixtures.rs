@@ -0,0 +1,34 @@
+//! Fixtures shared by the integration tests of misty-vm and its bindings.
+
+use misty_vm::{
+    client::MistyClient,
+    services::MistyServiceManager,
+    states::MistyStateManager,
+    views::{MistyViewModelManager, MistyViewTrait},
+    MistyState,
+};
+
+use crate::runtime::DeterministicRuntime;
+
+/// The state of most small test apps.
+#[derive(Debug, Default, Clone, MistyState)]
+pub struct CounterState {
+    pub count: i32,
+}
+
+/// A client without services, whose async tasks run on a [`DeterministicRuntime`] that no test
+/// drives. For tests that spawn no async tasks.
+pub fn new_client<R>(
+    view_manager: MistyViewModelManager<R>,
+    state_manager: MistyStateManager,
+) -> MistyClient<R>
+where
+    R: MistyViewTrait,
+{
+    MistyClient::new(
+        view_manager,
+        state_manager,
+        MistyServiceManager::builder().build(),
+        DeterministicRuntime::new().adapter(),
+    )
+}
@@ -1,95 +1,123 @@
+pub mod fixtures;
+pub mod runtime;
+
 use std::{
-    collections::HashMap,
-    sync::{atomic::AtomicU64, Arc, Mutex},
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
 };
 
-use futures::future::BoxFuture;
 use misty_vm::{
-    async_task::IAsyncTaskRuntimeAdapter,
+    async_task::{IAsyncTaskRuntimeAdapter, MistyLiveAsyncTasks},
     client::{MistyClientAccessor, SingletonMistyClientPod},
-    controllers::{ControllerRet, MistyController},
+    controllers::{
+        ControllerRet, MistyAsyncController, MistyAsyncControllerError, MistyController,
+    },
+    errors::MistyErrorEvent,
     resources::{MistyResourceId, ResourceUpdateAction},
+    runtimes::MistyTokioAsyncTaskAdapter,
     services::MistyServiceManager,
     signals::MistySignal,
     states::MistyStateManager,
-    views::MistyViewModelManager,
+    views::{MistyViewModelManager, MistyViewTrait},
 };
 
 #[derive(Clone)]
 pub struct TestAppContainer<R>
 where
-    R: Clone + Default,
+    R: MistyViewTrait,
 {
     app: Arc<SingletonMistyClientPod<R>>,
     state: Arc<Mutex<R>>,
     resources: Arc<Mutex<HashMap<MistyResourceId, Vec<u8>>>>,
-    apply_view: Arc<dyn Fn(R, &mut R) + Send + Sync + 'static>,
+    pending: Arc<Mutex<PendingControllerRets<R>>>,
+    /// Bumped whenever a result is applied to `state`.
+    applied: Arc<tokio::sync::watch::Sender<u64>>,
 }
 pub struct TestApp<R>
 where
-    R: Clone + Default,
+    R: MistyViewTrait,
 {
     app: TestAppContainer<R>,
 }
 
-pub struct TokioAsyncTaskAdapter<R>
+/// Results returned out of order by concurrent controller calls, waiting to be applied.
+struct PendingControllerRets<R>
 where
-    R: Clone + Default,
+    R: MistyViewTrait,
 {
-    _app: TestAppContainer<R>,
-    alloc: AtomicU64,
-    store: Arc<Mutex<HashMap<u64, tokio::task::JoinHandle<()>>>>,
+    next_seq: u64,
+    rets: BTreeMap<u64, ControllerRet<R>>,
 }
 
-impl<R> IAsyncTaskRuntimeAdapter for TokioAsyncTaskAdapter<R>
+impl<R> Default for TestAppContainer<R>
 where
-    R: Clone + Default + Send + Sync,
+    R: MistyViewTrait,
 {
-    fn spawn(&self, future: BoxFuture<'static, ()>) -> u64 {
-        let handle = tokio::spawn(future);
-        let id = self.alloc.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        {
-            let mut w = self.store.lock().unwrap();
-            w.insert(id, handle);
-        }
-        id
-    }
-
-    fn spawn_local(&self, future: futures::prelude::future::LocalBoxFuture<'static, ()>) -> u64 {
-        panic!("spawn_local is not implemented yet");
-    }
-
-    fn try_abort(&self, task_id: u64) {
-        let handle = {
-            let mut w = self.store.lock().unwrap();
-            w.remove(&task_id)
-        };
-        if let Some(handle) = handle {
-            handle.abort();
-        }
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl<R> TestAppContainer<R>
 where
-    R: Default + Clone + Send + Sync + 'static,
+    R: MistyViewTrait,
 {
-    pub fn new(apply_view: impl Fn(R, &mut R) + Send + Sync + 'static) -> Self {
+    pub fn new() -> Self {
         Self {
             app: Arc::new(SingletonMistyClientPod::new()),
             state: Default::default(),
             resources: Default::default(),
-            apply_view: Arc::new(apply_view),
+            pending: Arc::new(Mutex::new(PendingControllerRets {
+                next_seq: 1,
+                rets: Default::default(),
+            })),
+            applied: Arc::new(tokio::sync::watch::Sender::new(0)),
         }
     }
 
-    pub fn call_controller<Controller, Arg, E>(&self, controller: Controller, arg: Arg)
+    /// Waits until the view satisfies `until`, checking it after every applied result.
+    pub async fn wait_state(&self, until: impl Fn(&R) -> bool) {
+        let mut applied = self.applied.subscribe();
+        loop {
+            let done = until(&self.state.lock().unwrap());
+            if done {
+                return;
+            }
+            // the sender is owned by self, so this only returns on changes
+            let _ = applied.changed().await;
+        }
+    }
+
+    pub fn call_controller<Controller, Arg, E, T>(&self, controller: Controller, arg: Arg) -> T
     where
-        Controller: MistyController<Arg, E>,
+        Controller: MistyController<Arg, E, T>,
         E: std::fmt::Debug,
     {
         let ret = self.app.call_controller(controller, arg).unwrap();
-        self.apply(ret);
+        let value = ret.value;
+        self.apply(ControllerRet {
+            changed_view: ret.changed_view,
+            changed_resources: ret.changed_resources,
+            seq: ret.seq,
+            value: (),
+        });
+        value
+    }
+
+    pub async fn call_controller_async<Controller, Arg, E>(
+        &self,
+        controller: Controller,
+        arg: Arg,
+    ) -> Result<(), MistyAsyncControllerError<E>>
+    where
+        Controller: MistyAsyncController<Arg, E>,
+        E: Send + 'static,
+    {
+        self.app.call_controller_async(controller, arg).await
+    }
+
+    pub fn destroy(&self) {
+        self.app.destroy();
     }
 
     pub fn flush_schedules(&self) {
@@ -106,11 +134,35 @@ where
         self.app.accessor()
     }
 
+    pub fn on_error(&self, f: impl Fn(&MistyErrorEvent) + Send + Sync + 'static) {
+        self.app.on_error(f);
+    }
+
+    pub fn live_async_tasks(&self) -> Vec<MistyLiveAsyncTasks> {
+        self.app.live_async_tasks()
+    }
+
     fn apply(&self, ret: ControllerRet<R>) {
+        let Some(seq) = ret.seq else {
+            return;
+        };
+        let mut pending = self.pending.lock().unwrap();
+        pending.rets.insert(seq, ret);
+        loop {
+            let next_seq = pending.next_seq;
+            let Some(ret) = pending.rets.remove(&next_seq) else {
+                break;
+            };
+            pending.next_seq += 1;
+            self.apply_in_order(ret);
+        }
+    }
+
+    fn apply_in_order(&self, ret: ControllerRet<R>) {
         {
             let mut state_guard = self.state.lock().unwrap();
             if let Some(changed_view) = ret.changed_view {
-                (self.apply_view)(changed_view, &mut state_guard);
+                state_guard.apply_patch(changed_view);
             }
         }
 
@@ -128,11 +180,12 @@ where
                 }
             }
         }
+        self.applied.send_modify(|applied| *applied += 1);
     }
 }
 impl<R> TestApp<R>
 where
-    R: Default + Clone + Send + Sync + 'static,
+    R: MistyViewTrait,
 {
     pub fn new(
         view_manager: MistyViewModelManager<R>,
@@ -140,12 +193,24 @@ where
         state_manager: MistyStateManager,
         app_container: TestAppContainer<R>,
     ) -> Self {
-        let adapter = TokioAsyncTaskAdapter {
-            _app: app_container.clone(),
-            alloc: Default::default(),
-            store: Default::default(),
-        };
+        Self::with_async_task_runtime(
+            view_manager,
+            service_manager,
+            state_manager,
+            app_container,
+            MistyTokioAsyncTaskAdapter::new(),
+        )
+    }
 
+    /// Like [`TestApp::new`], but spawns async tasks on `adapter`, such as the adapter of a
+    /// [`runtime::DeterministicRuntime`].
+    pub fn with_async_task_runtime(
+        view_manager: MistyViewModelManager<R>,
+        service_manager: MistyServiceManager,
+        state_manager: MistyStateManager,
+        app_container: TestAppContainer<R>,
+        adapter: impl IAsyncTaskRuntimeAdapter + Send + Sync + 'static,
+    ) -> Self {
         app_container
             .app
             .create(view_manager, state_manager, service_manager, adapter);
@@ -0,0 +1,287 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    thread::ThreadId,
+    time::Duration,
+};
+
+use futures::{
+    future::{BoxFuture, LocalBoxFuture},
+    task::ArcWake,
+};
+use misty_vm::async_task::{IAsyncTaskRuntimeAdapter, LocalFutureFn};
+
+/// A single-threaded runtime with a virtual clock. Spawned tasks only run in
+/// [`DeterministicRuntime::run_until_idle`] and [`DeterministicRuntime::advance`], in the order
+/// they are woken, so tests run the same way every time.
+#[derive(Clone)]
+pub struct DeterministicRuntime {
+    inner: Arc<RuntimeInner>,
+}
+
+/// The adapter of a [`DeterministicRuntime`], passed to the client.
+pub struct DeterministicAsyncTaskAdapter {
+    inner: Arc<RuntimeInner>,
+}
+
+struct RuntimeInner {
+    owner: ThreadId,
+    tasks: Mutex<Tasks>,
+    ready: Arc<Mutex<VecDeque<u64>>>,
+    clock: Arc<Mutex<Clock>>,
+}
+
+#[derive(Default)]
+struct Tasks {
+    alloc: u64,
+    futures: HashMap<u64, LocalTask>,
+    /// The task being polled, which is out of `futures` until the poll returns.
+    polling: Option<u64>,
+    abort_polling: bool,
+}
+
+struct LocalTask(LocalBoxFuture<'static, ()>);
+
+// SAFETY: tasks are only spawned, polled and dropped on the owner thread of the runtime
+unsafe impl Send for LocalTask {}
+
+#[derive(Default)]
+struct Clock {
+    now: Duration,
+    alloc: u64,
+    timers: BTreeMap<(Duration, u64), Waker>,
+}
+
+struct TaskWaker {
+    id: u64,
+    ready: Arc<Mutex<VecDeque<u64>>>,
+}
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.ready.lock().unwrap().push_back(arc_self.id);
+    }
+}
+
+/// Completes when the virtual clock reaches its deadline.
+pub struct Sleep {
+    clock: Arc<Mutex<Clock>>,
+    deadline: Duration,
+    timer: Option<(Duration, u64)>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut clock = this.clock.lock().unwrap();
+        if clock.now >= this.deadline {
+            return Poll::Ready(());
+        }
+        let key = match this.timer {
+            Some(key) => key,
+            None => {
+                clock.alloc += 1;
+                let key = (this.deadline, clock.alloc);
+                this.timer = Some(key);
+                key
+            }
+        };
+        clock.timers.insert(key, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(key) = self.timer {
+            self.clock.lock().unwrap().timers.remove(&key);
+        }
+    }
+}
+
+impl Default for DeterministicRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeterministicRuntime {
+    /// The runtime can only be used on the thread creating it.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(RuntimeInner {
+                owner: std::thread::current().id(),
+                tasks: Default::default(),
+                ready: Default::default(),
+                clock: Default::default(),
+            }),
+        }
+    }
+
+    pub fn adapter(&self) -> DeterministicAsyncTaskAdapter {
+        DeterministicAsyncTaskAdapter {
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Virtual time elapsed since the runtime is created.
+    pub fn now(&self) -> Duration {
+        self.inner.clock.lock().unwrap().now
+    }
+
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        self.inner.sleep(duration)
+    }
+
+    /// Runs woken tasks until every task is finished or waiting.
+    pub fn run_until_idle(&self) {
+        self.inner.assert_owner();
+        loop {
+            let id = self.inner.ready.lock().unwrap().pop_front();
+            let Some(id) = id else {
+                break;
+            };
+            self.inner.poll(id);
+        }
+    }
+
+    /// Moves the clock forward by `duration`, firing timers in deadline order and running the
+    /// tasks they wake.
+    pub fn advance(&self, duration: Duration) {
+        let target = self.now() + duration;
+        loop {
+            self.run_until_idle();
+            let fired = {
+                let mut clock = self.inner.clock.lock().unwrap();
+                let Some((&(deadline, _), _)) = clock.timers.first_key_value() else {
+                    break;
+                };
+                if deadline > target {
+                    break;
+                }
+                clock.now = deadline;
+                let later = clock.timers.split_off(&(deadline, u64::MAX));
+                std::mem::replace(&mut clock.timers, later)
+            };
+            for (_, waker) in fired.into_iter() {
+                waker.wake();
+            }
+        }
+        self.inner.clock.lock().unwrap().now = target;
+        self.run_until_idle();
+    }
+
+    /// Tasks that are neither finished nor aborted.
+    pub fn running_tasks(&self) -> usize {
+        let tasks = self.inner.tasks.lock().unwrap();
+        tasks.futures.len() + tasks.polling.iter().count()
+    }
+}
+
+impl RuntimeInner {
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let now = self.clock.lock().unwrap().now;
+        Sleep {
+            clock: self.clock.clone(),
+            deadline: now + duration,
+            timer: None,
+        }
+    }
+
+    fn assert_owner(&self) {
+        if std::thread::current().id() != self.owner {
+            panic!("deterministic runtime is used outside its thread");
+        }
+    }
+
+    fn spawn(&self, future: LocalBoxFuture<'static, ()>) -> u64 {
+        self.assert_owner();
+        let id = {
+            let mut tasks = self.tasks.lock().unwrap();
+            tasks.alloc += 1;
+            let id = tasks.alloc;
+            tasks.futures.insert(id, LocalTask(future));
+            id
+        };
+        self.ready.lock().unwrap().push_back(id);
+        id
+    }
+
+    fn poll(&self, id: u64) {
+        let task = {
+            let mut tasks = self.tasks.lock().unwrap();
+            let task = tasks.futures.remove(&id);
+            if task.is_some() {
+                tasks.polling = Some(id);
+                tasks.abort_polling = false;
+            }
+            task
+        };
+        let Some(mut task) = task else {
+            return;
+        };
+
+        // the lock is released while polling, since tasks may spawn or abort tasks
+        let waker = futures::task::waker(Arc::new(TaskWaker {
+            id,
+            ready: self.ready.clone(),
+        }));
+        let mut cx = Context::from_waker(&waker);
+        let res = task.0.as_mut().poll(&mut cx);
+
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.polling = None;
+        if res.is_pending() && !tasks.abort_polling {
+            tasks.futures.insert(id, task);
+        }
+    }
+
+    fn abort(&self, id: u64) {
+        self.assert_owner();
+        let task = {
+            let mut tasks = self.tasks.lock().unwrap();
+            if tasks.polling == Some(id) {
+                tasks.abort_polling = true;
+            }
+            tasks.futures.remove(&id)
+        };
+        drop(task);
+    }
+}
+
+impl Drop for RuntimeInner {
+    fn drop(&mut self) {
+        let futures = std::mem::take(&mut self.tasks.get_mut().unwrap().futures);
+        if std::thread::current().id() != self.owner {
+            // local tasks must not be dropped on other threads
+            std::mem::forget(futures);
+        }
+    }
+}
+
+impl IAsyncTaskRuntimeAdapter for DeterministicAsyncTaskAdapter {
+    fn spawn(&self, future: BoxFuture<'static, ()>) -> u64 {
+        self.inner.spawn(future)
+    }
+
+    fn spawn_local(&self, future_fn: LocalFutureFn) -> u64 {
+        self.inner.spawn(future_fn())
+    }
+
+    fn try_abort(&self, task_id: u64) {
+        self.inner.abort(task_id);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(self.inner.sleep(duration))
+    }
+
+    fn now(&self) -> Duration {
+        self.inner.clock.lock().unwrap().now
+    }
+}
@@ -0,0 +1,164 @@
+use std::{convert::Infallible, time::Duration};
+
+use misty_vm::{async_task::MistyAsyncTaskContext, states::MistyStateTrait, MistyState, MistyView};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct ItemsState {
+    pub loading: bool,
+    pub titles: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub loading: bool,
+    pub titles: Vec<String>,
+}
+
+async fn fetch_title(id: i32) -> Result<String, String> {
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    if id < 0 {
+        return Err("not found".to_string());
+    }
+    Ok(format!("item {}", id))
+}
+
+async fn controller_load(ctx: MistyAsyncTaskContext, id: i32) -> Result<(), String> {
+    ctx.schedule(|handle| {
+        ItemsState::update(handle, |state| {
+            state.loading = true;
+        });
+        Ok::<(), Infallible>(())
+    });
+    let title = fetch_title(id).await;
+    ctx.schedule(|handle| {
+        ItemsState::update(handle, |state| {
+            state.loading = false;
+        });
+        Ok::<(), Infallible>(())
+    });
+    let title = title?;
+    ctx.schedule(move |handle| {
+        ItemsState::update(handle, |state| {
+            state.titles.push(title);
+        });
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+async fn controller_wait_forever(_ctx: MistyAsyncTaskContext, _arg: ()) -> Result<(), Infallible> {
+    futures::future::pending::<()>().await;
+    Ok(())
+}
+
+async fn controller_panic(_ctx: MistyAsyncTaskContext, _arg: ()) -> Result<(), Infallible> {
+    tokio::task::yield_now().await;
+    panic!("generate panic!");
+}
+
+fn items_view_model(state: &ItemsState, root: &mut RootViewModelState) {
+    root.loading = state.loading;
+    root.titles = state.titles.clone();
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use std::sync::{Arc, Mutex};
+
+    use misty_vm::{
+        controllers::MistyAsyncControllerError,
+        errors::{MistyErrorEvent, MistyErrorKind},
+        misty_states,
+        services::MistyServiceManager,
+        states::MistyStateManager,
+        views::MistyViewModelManager,
+    };
+    use misty_vm_test::{TestApp, TestAppContainer};
+
+    use crate::{
+        controller_load, controller_panic, controller_wait_forever, items_view_model, ItemsState,
+        RootViewModelState,
+    };
+
+    fn build_app() -> TestApp<RootViewModelState> {
+        let app_container = TestAppContainer::new();
+        let view_manager = MistyViewModelManager::builder()
+            .register(items_view_model)
+            .build();
+        let service_manager = MistyServiceManager::builder().build();
+        let state_manager = MistyStateManager::new(misty_states!(ItemsState));
+        TestApp::new(view_manager, service_manager, state_manager, app_container)
+    }
+
+    #[tokio::test]
+    async fn test_async_controller() {
+        let app = build_app();
+        app.app()
+            .call_controller_async(controller_load, 1)
+            .await
+            .unwrap();
+        app.app()
+            .call_controller_async(controller_load, 2)
+            .await
+            .unwrap();
+
+        let state = app.state();
+        assert!(!state.loading);
+        assert_eq!(
+            state.titles,
+            vec!["item 1".to_string(), "item 2".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_async_controller_error() {
+        let app = build_app();
+        let ret = app.app().call_controller_async(controller_load, -1).await;
+        assert!(matches!(ret, Err(MistyAsyncControllerError::Failed(e)) if e == "not found"));
+
+        let state = app.state();
+        assert!(!state.loading);
+        assert!(state.titles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_async_controller_cancelled_on_destroy() {
+        let app = build_app();
+        let container = app.app();
+        let handle = tokio::spawn(async move {
+            container
+                .call_controller_async(controller_wait_forever, ())
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        app.app().destroy();
+
+        let ret = handle.await.unwrap();
+        assert!(matches!(ret, Err(MistyAsyncControllerError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_async_controller_panicked() {
+        std::env::set_var("RUST_BACKTRACE", "0");
+        let app = build_app();
+        let events: Arc<Mutex<Vec<MistyErrorEvent>>> = Default::default();
+        {
+            let events = events.clone();
+            app.app()
+                .on_error(move |event| events.lock().unwrap().push(event.clone()));
+        }
+
+        let ret = app.app().call_controller_async(controller_panic, ()).await;
+        assert!(
+            matches!(ret, Err(MistyAsyncControllerError::Panicked(message)) if message == "generate panic!")
+        );
+        let events = events.lock().unwrap().clone();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].kind,
+            MistyErrorKind::Panic("generate panic!".to_string())
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::convert::Infallible;
+
+use misty_vm::{
+    async_task::MistyAsyncTaskTrait, controllers::MistyControllerContext, states::MistyStateTrait,
+    MistyAsyncTask, MistyState, MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct LogState {
+    pub logs: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub logs: Vec<String>,
+}
+
+#[derive(Debug, MistyAsyncTask)]
+#[misty(cancel_grace_period_ms = 500)]
+struct WatchAsyncTask;
+
+#[derive(Debug, MistyAsyncTask)]
+struct ImmediateAsyncTask;
+
+fn controller_start_watch(ctx: MistyControllerContext, arg: String) -> Result<(), Infallible> {
+    WatchAsyncTask::spawn_once(&ctx, move |ctx| async move {
+        ctx.cancelled().await;
+        ctx.schedule(move |handle| {
+            LogState::update(handle, |state| {
+                state.logs.push(format!("cancelled {}", arg));
+            });
+            Ok::<(), Infallible>(())
+        });
+        // ignores cancellation after cleaning up, so it is aborted by the grace period
+        futures::future::pending::<()>().await;
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn controller_cancel_watch(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
+    WatchAsyncTask::cancel_all(&ctx);
+    Ok(())
+}
+
+fn controller_start_immediate(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
+    ImmediateAsyncTask::spawn_once(&ctx, move |ctx| async move {
+        ctx.cancelled().await;
+        ctx.schedule(move |handle| {
+            LogState::update(handle, |state| {
+                state.logs.push("cancelled immediate".to_string());
+            });
+            Ok::<(), Infallible>(())
+        });
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn log_view_model(state: &LogState, root: &mut RootViewModelState) {
+    root.logs = state.logs.clone();
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use misty_vm::{
+        misty_states, services::MistyServiceManager, states::MistyStateManager,
+        views::MistyViewModelManager,
+    };
+    use misty_vm_test::{runtime::DeterministicRuntime, TestApp, TestAppContainer};
+
+    use crate::{
+        controller_cancel_watch, controller_start_immediate, controller_start_watch,
+        log_view_model, LogState, RootViewModelState,
+    };
+
+    fn build_app(runtime: &DeterministicRuntime) -> TestApp<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(log_view_model)
+            .build();
+        let service_manager = MistyServiceManager::builder().build();
+        let state_manager = MistyStateManager::new(misty_states!(LogState));
+
+        TestApp::with_async_task_runtime(
+            view_manager,
+            service_manager,
+            state_manager,
+            TestAppContainer::new(),
+            runtime.adapter(),
+        )
+    }
+
+    #[test]
+    fn test_grace_period() {
+        let runtime = DeterministicRuntime::new();
+        let app = build_app(&runtime);
+
+        app.app()
+            .call_controller(controller_start_watch, "a".to_string());
+        runtime.run_until_idle();
+        assert_eq!(runtime.running_tasks(), 1);
+
+        // the task of "a" is cancelled, and aborted by a timer after the grace period
+        app.app()
+            .call_controller(controller_start_watch, "b".to_string());
+        runtime.run_until_idle();
+        app.app().flush_schedules();
+        assert_eq!(app.state().logs, vec!["cancelled a"]);
+        assert_eq!(runtime.running_tasks(), 3);
+
+        runtime.advance(Duration::from_millis(499));
+        assert_eq!(runtime.running_tasks(), 3);
+        runtime.advance(Duration::from_millis(1));
+        assert_eq!(runtime.running_tasks(), 1);
+
+        app.app().call_controller(controller_cancel_watch, ());
+        runtime.advance(Duration::from_millis(500));
+        app.app().flush_schedules();
+        assert_eq!(app.state().logs, vec!["cancelled a", "cancelled b"]);
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_no_grace_period() {
+        let runtime = DeterministicRuntime::new();
+        let app = build_app(&runtime);
+
+        app.app().call_controller(controller_start_immediate, ());
+        runtime.run_until_idle();
+        app.app().call_controller(controller_start_immediate, ());
+        assert_eq!(runtime.running_tasks(), 1);
+
+        runtime.advance(Duration::from_secs(1));
+        app.app().flush_schedules();
+        assert!(app.state().logs.is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use misty_vm::{
+    async_task::MistyAsyncTaskTrait, controllers::MistyControllerContext, MistyAsyncTask,
+    MistyState, MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct ClockState {}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {}
+
+#[derive(Debug, MistyAsyncTask)]
+struct ClockAsyncTask;
+
+type Logs = Arc<Mutex<Vec<u128>>>;
+
+fn controller_sleep(ctx: MistyControllerContext, arg: Logs) -> Result<(), Infallible> {
+    ClockAsyncTask::spawn(&ctx, move |ctx| async move {
+        ctx.sleep(Duration::from_millis(300)).await;
+        arg.lock().unwrap().push(ctx.now().as_millis());
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn controller_interval(ctx: MistyControllerContext, arg: Logs) -> Result<(), Infallible> {
+    ClockAsyncTask::spawn(&ctx, move |ctx| async move {
+        let mut interval = ctx.interval(Duration::from_millis(100));
+        // the work after a tick takes 250ms, then 80ms, then nothing
+        for work in [250, 80, 0, 0] {
+            interval.tick().await;
+            arg.lock().unwrap().push(ctx.now().as_millis());
+            ctx.sleep(Duration::from_millis(work)).await;
+        }
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn controller_zero_interval(ctx: MistyControllerContext, arg: Logs) -> Result<(), Infallible> {
+    ClockAsyncTask::spawn(&ctx, move |ctx| async move {
+        let mut interval = ctx.interval(Duration::ZERO);
+        interval.tick().await;
+        arg.lock().unwrap().push(ctx.now().as_millis());
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn clock_view_model(_state: &ClockState, _root: &mut RootViewModelState) {}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use misty_vm::{
+        client::MistyClient, misty_states, services::MistyServiceManager,
+        states::MistyStateManager, views::MistyViewModelManager,
+    };
+    use misty_vm_test::runtime::DeterministicRuntime;
+
+    use crate::{
+        clock_view_model, controller_interval, controller_sleep, controller_zero_interval,
+        ClockState, Logs, RootViewModelState,
+    };
+
+    fn build_client(runtime: &DeterministicRuntime) -> MistyClient<RootViewModelState> {
+        MistyClient::new(
+            MistyViewModelManager::builder()
+                .register(clock_view_model)
+                .build(),
+            MistyStateManager::new(misty_states!(ClockState)),
+            MistyServiceManager::builder().build(),
+            runtime.adapter(),
+        )
+    }
+
+    #[test]
+    fn test_sleep() {
+        let runtime = DeterministicRuntime::new();
+        let client = build_client(&runtime);
+        let logs: Logs = Default::default();
+
+        runtime.advance(Duration::from_millis(50));
+        client
+            .call_controller(controller_sleep, logs.clone())
+            .unwrap();
+        runtime.advance(Duration::from_millis(299));
+        assert!(logs.lock().unwrap().is_empty());
+        runtime.advance(Duration::from_millis(1));
+        assert_eq!(logs.lock().unwrap().clone(), vec![350]);
+    }
+
+    #[test]
+    fn test_interval() {
+        let runtime = DeterministicRuntime::new();
+        let client = build_client(&runtime);
+        let logs: Logs = Default::default();
+
+        client
+            .call_controller(controller_interval, logs.clone())
+            .unwrap();
+        runtime.advance(Duration::from_secs(1));
+
+        // a tick late by a whole period restarts the ticks, a tick late by less keeps them
+        assert_eq!(logs.lock().unwrap().clone(), vec![0, 250, 350, 450]);
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_zero_interval() {
+        std::env::set_var("RUST_BACKTRACE", "0");
+        let runtime = DeterministicRuntime::new();
+        let client = build_client(&runtime);
+        let logs: Logs = Default::default();
+
+        client
+            .call_controller(controller_zero_interval, logs.clone())
+            .unwrap();
+        runtime.advance(Duration::from_secs(1));
+
+        // the task panics instead of ticking in a busy loop
+        assert!(logs.lock().unwrap().is_empty());
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+}
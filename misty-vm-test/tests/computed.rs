@@ -0,0 +1,200 @@
+use std::{cell::Cell, convert::Infallible};
+
+use misty_vm::{
+    controllers::MistyControllerContext,
+    states::{MistyComputedTrait, MistyStateTrait},
+    MistyState, MistyView,
+};
+use misty_vm_test::fixtures::CounterState;
+
+thread_local! {
+    // computed states are refreshed on the reading thread
+    static TOTAL_COMPUTE_COUNT: Cell<usize> = const { Cell::new(0) };
+    static LABEL_VIEW_COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Debug, Clone, MistyState)]
+struct PriceState {
+    pub price: i32,
+}
+
+impl Default for PriceState {
+    fn default() -> Self {
+        Self { price: 10 }
+    }
+}
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct OtherState {
+    pub value: i32,
+}
+
+#[derive(Debug, Default, Clone, MistyState)]
+#[misty(computed)]
+struct TotalState {
+    pub total: i32,
+}
+
+impl MistyComputedTrait for TotalState {
+    type Deps<'a> = (&'a CounterState, &'a PriceState);
+
+    fn compute((counter, price): Self::Deps<'_>) -> Self {
+        TOTAL_COMPUTE_COUNT.set(TOTAL_COMPUTE_COUNT.get() + 1);
+        Self {
+            total: counter.count * price.price,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, MistyState)]
+#[misty(computed)]
+struct LabelState {
+    pub label: String,
+}
+
+impl MistyComputedTrait for LabelState {
+    type Deps<'a> = &'a TotalState;
+
+    fn compute(total: Self::Deps<'_>) -> Self {
+        Self {
+            label: format!("total: {}", total.total),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub label: String,
+}
+
+fn controller_add(ctx: MistyControllerContext, arg: i32) -> Result<(), String> {
+    CounterState::update(&ctx, |state| {
+        state.count += arg;
+    });
+    if arg < 0 {
+        return Err("negative".to_string());
+    }
+    Ok(())
+}
+
+fn controller_set_price(ctx: MistyControllerContext, arg: i32) -> Result<(), Infallible> {
+    PriceState::update(&ctx, |state| {
+        state.price = arg;
+    });
+    Ok(())
+}
+
+fn controller_set_other(ctx: MistyControllerContext, arg: i32) -> Result<(), Infallible> {
+    OtherState::update(&ctx, |state| {
+        state.value = arg;
+    });
+    Ok(())
+}
+
+fn controller_set_total(ctx: MistyControllerContext, arg: i32) -> Result<(), Infallible> {
+    TotalState::update(&ctx, |state| {
+        state.total = arg;
+    });
+    Ok(())
+}
+
+fn label_view_model(state: &LabelState, root: &mut RootViewModelState) {
+    LABEL_VIEW_COUNT.set(LABEL_VIEW_COUNT.get() + 1);
+    root.label = state.label.clone();
+}
+
+#[cfg(test)]
+mod test {
+    use misty_vm::{
+        client::MistyClient,
+        misty_states,
+        states::{MistyStateManager, MistyStateTrait},
+        views::MistyViewModelManager,
+    };
+    use misty_vm_test::fixtures::new_client;
+
+    use crate::{
+        controller_add, controller_set_other, controller_set_price, controller_set_total,
+        label_view_model, CounterState, LabelState, OtherState, PriceState, RootViewModelState,
+        TotalState, LABEL_VIEW_COUNT, TOTAL_COMPUTE_COUNT,
+    };
+
+    fn build_client() -> MistyClient<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(label_view_model)
+            .build();
+        let state_manager = MistyStateManager::new(misty_states!(
+            CounterState,
+            PriceState,
+            OtherState,
+            TotalState,
+            LabelState
+        ));
+        new_client(view_manager, state_manager)
+    }
+
+    fn total(client: &MistyClient<RootViewModelState>) -> i32 {
+        client
+            .accessor()
+            .get()
+            .map(|pod| TotalState::map(pod.handle(), |state| state.total))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_computed_states() {
+        let client = build_client();
+        let ret = client.call_controller(controller_add, 2).unwrap();
+        assert_eq!(
+            ret.changed_view.unwrap().label.as_deref(),
+            Some("total: 20")
+        );
+
+        let ret = client.call_controller(controller_set_price, 3).unwrap();
+        assert_eq!(ret.changed_view.unwrap().label.as_deref(), Some("total: 6"));
+
+        let ret = client.call_controller(controller_set_other, 1).unwrap();
+        assert!(ret.changed_view.is_none());
+        assert_eq!(total(&client), 6);
+
+        let ret = client.call_controller(controller_add, -1);
+        assert!(ret.is_err());
+        assert_eq!(total(&client), 6);
+    }
+
+    #[test]
+    fn test_computed_state_is_cached() {
+        let client = build_client();
+        client.call_controller(controller_add, 1).unwrap();
+        let count = TOTAL_COMPUTE_COUNT.get();
+
+        assert_eq!(total(&client), 10);
+        client.call_controller(controller_set_other, 1).unwrap();
+        assert_eq!(total(&client), 10);
+        assert_eq!(TOTAL_COMPUTE_COUNT.get(), count);
+
+        client.call_controller(controller_add, 1).unwrap();
+        assert_eq!(total(&client), 20);
+        assert_eq!(TOTAL_COMPUTE_COUNT.get(), count + 1);
+    }
+
+    #[test]
+    fn test_rollback_does_not_update_computed() {
+        let client = build_client();
+        client.call_controller(controller_add, 1).unwrap();
+        let count = LABEL_VIEW_COUNT.get();
+
+        assert!(client.call_controller(controller_add, -1).is_err());
+        assert_eq!(total(&client), 10);
+        let ret = client.call_controller(controller_set_other, 1).unwrap();
+        assert!(ret.changed_view.is_none());
+        assert_eq!(LABEL_VIEW_COUNT.get(), count);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot update computed state")]
+    fn test_update_computed_state() {
+        let client = build_client();
+        let _ = client.call_controller(controller_set_total, 1);
+    }
+}
@@ -6,7 +6,7 @@ use misty_vm::states::MistyStateTrait;
 use misty_vm::{
     async_task::MistyAsyncTaskTrait, controllers::MistyControllerContext, misty_service,
 };
-use misty_vm::{MistyAsyncTask, MistyState};
+use misty_vm::{MistyAsyncTask, MistyState, MistyView};
 use rand::{
     distributions::{Distribution, Uniform},
     Rng,
@@ -18,7 +18,7 @@ struct GlobalState {
     pub done: bool,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, MistyView)]
 struct RootViewModelState {
     pub store: HashMap<i32, bool>,
     pub done: bool,
@@ -198,12 +198,7 @@ mod test {
     use tokio::task::JoinSet;
 
     fn build_app() -> TestApp<RootViewModelState> {
-        let app_container = TestAppContainer::<RootViewModelState>::new(|changed, state| {
-            for (k, v) in changed.store {
-                state.store.insert(k, v);
-            }
-            state.done = changed.done;
-        });
+        let app_container = TestAppContainer::<RootViewModelState>::new();
 
         let view_manager = MistyViewModelManager::builder()
             .register(global_view_model)
@@ -221,15 +216,11 @@ mod test {
     }
 
     async fn wait_done(app: &TestApp<RootViewModelState>) -> bool {
-        for _ in 0..50 {
-            let state = app.state();
-            if state.done {
-                return true;
-            }
-
-            tokio::time::sleep(Duration::from_millis(100)).await;
-        }
-        return false;
+        let app = app.app();
+        let done = app.wait_state(|state| state.done);
+        tokio::time::timeout(Duration::from_secs(5), done)
+            .await
+            .is_ok()
     }
 
     struct FakeAServiceImpl {
@@ -0,0 +1,261 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use misty_vm::{
+    async_task::{IAsyncTaskRuntimeAdapter, LocalFutureFn},
+    client::AsReadonlyMistyClientHandle,
+    controllers::MistyControllerContext,
+    BoxFuture, MistyState, MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct SearchState {}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {}
+
+type Logs = Arc<Mutex<Vec<String>>>;
+
+/// Runs spawned tasks to completion inside `spawn`, with timers that fire at once and move
+/// the clock forward.
+#[derive(Default)]
+struct InlineAsyncTaskAdapter {
+    now: Mutex<Duration>,
+}
+
+impl IAsyncTaskRuntimeAdapter for InlineAsyncTaskAdapter {
+    fn spawn(&self, future: BoxFuture<'static, ()>) -> u64 {
+        futures::executor::block_on(future);
+        0
+    }
+
+    fn spawn_local(&self, future_fn: LocalFutureFn) -> u64 {
+        futures::executor::block_on(future_fn());
+        0
+    }
+
+    fn try_abort(&self, _task_id: u64) {}
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        *self.now.lock().unwrap() += duration;
+        Box::pin(futures::future::ready(()))
+    }
+
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
+
+fn controller_schedule_after(
+    ctx: MistyControllerContext,
+    arg: (Logs, &'static str),
+) -> Result<(), Infallible> {
+    let (logs, label) = arg;
+    ctx.handle()
+        .readonly_handle()
+        .schedule_after(Duration::from_millis(100), move |_handle| {
+            logs.lock().unwrap().push(label.to_string());
+            Ok::<(), Infallible>(())
+        });
+    Ok(())
+}
+
+fn controller_search(
+    ctx: MistyControllerContext,
+    arg: (Logs, &'static str),
+) -> Result<(), Infallible> {
+    let (logs, keyword) = arg;
+    ctx.handle().readonly_handle().schedule_debounced(
+        "search",
+        Duration::from_millis(100),
+        move |_handle| {
+            logs.lock().unwrap().push(keyword.to_string());
+            Ok::<(), Infallible>(())
+        },
+    );
+    Ok(())
+}
+
+fn controller_scroll(
+    ctx: MistyControllerContext,
+    arg: (Logs, &'static str),
+) -> Result<(), Infallible> {
+    let (logs, offset) = arg;
+    ctx.handle().readonly_handle().schedule_throttled(
+        "scroll",
+        Duration::from_millis(100),
+        move |_handle| {
+            logs.lock().unwrap().push(offset.to_string());
+            Ok::<(), Infallible>(())
+        },
+    );
+    Ok(())
+}
+
+fn search_view_model(_state: &SearchState, _root: &mut RootViewModelState) {}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use misty_vm::{
+        client::MistyClient, misty_states, services::MistyServiceManager,
+        states::MistyStateManager, views::MistyViewModelManager,
+    };
+    use misty_vm_test::runtime::DeterministicRuntime;
+
+    use crate::{
+        controller_schedule_after, controller_scroll, controller_search, search_view_model,
+        InlineAsyncTaskAdapter, Logs, RootViewModelState, SearchState,
+    };
+
+    fn build_client(runtime: &DeterministicRuntime) -> MistyClient<RootViewModelState> {
+        let client = MistyClient::new(
+            MistyViewModelManager::builder()
+                .register(search_view_model)
+                .build(),
+            MistyStateManager::new(misty_states!(SearchState)),
+            MistyServiceManager::builder().build(),
+            runtime.adapter(),
+        );
+        client.on_signal(|_| {});
+        client
+    }
+
+    fn advance(
+        runtime: &DeterministicRuntime,
+        client: &MistyClient<RootViewModelState>,
+        logs: &Logs,
+        millis: u64,
+    ) -> Vec<String> {
+        runtime.advance(Duration::from_millis(millis));
+        client.flush_scheduled_tasks().unwrap();
+        logs.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn test_schedule_after() {
+        let runtime = DeterministicRuntime::new();
+        let client = build_client(&runtime);
+        let logs: Logs = Default::default();
+
+        client
+            .call_controller(controller_schedule_after, (logs.clone(), "a"))
+            .unwrap();
+        assert!(advance(&runtime, &client, &logs, 99).is_empty());
+        assert_eq!(advance(&runtime, &client, &logs, 1), vec!["a"]);
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_debounce() {
+        let runtime = DeterministicRuntime::new();
+        let client = build_client(&runtime);
+        let logs: Logs = Default::default();
+
+        for keyword in ["r", "ru", "rus"] {
+            client
+                .call_controller(controller_search, (logs.clone(), keyword))
+                .unwrap();
+            assert!(advance(&runtime, &client, &logs, 60).is_empty());
+        }
+        assert_eq!(advance(&runtime, &client, &logs, 40), vec!["rus"]);
+
+        client
+            .call_controller(controller_search, (logs.clone(), "rust"))
+            .unwrap();
+        assert_eq!(advance(&runtime, &client, &logs, 100), vec!["rus", "rust"]);
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_throttle() {
+        let runtime = DeterministicRuntime::new();
+        let client = build_client(&runtime);
+        let logs: Logs = Default::default();
+
+        // the first runs at once, the last in each window runs when it ends
+        for offset in ["0", "1", "2"] {
+            client
+                .call_controller(controller_scroll, (logs.clone(), offset))
+                .unwrap();
+            advance(&runtime, &client, &logs, 30);
+        }
+        assert_eq!(logs.lock().unwrap().clone(), vec!["0"]);
+        assert_eq!(advance(&runtime, &client, &logs, 10), vec!["0", "2"]);
+
+        client
+            .call_controller(controller_scroll, (logs.clone(), "3"))
+            .unwrap();
+        assert_eq!(advance(&runtime, &client, &logs, 99), vec!["0", "2"]);
+        assert_eq!(advance(&runtime, &client, &logs, 1), vec!["0", "2", "3"]);
+
+        // the window ends without a trailing handler, so the next one runs at once
+        advance(&runtime, &client, &logs, 100);
+        client
+            .call_controller(controller_scroll, (logs.clone(), "4"))
+            .unwrap();
+        assert_eq!(
+            advance(&runtime, &client, &logs, 0),
+            vec!["0", "2", "3", "4"]
+        );
+    }
+
+    #[test]
+    fn test_cancel_on_destroy() {
+        let runtime = DeterministicRuntime::new();
+        let client = build_client(&runtime);
+        let logs: Logs = Default::default();
+
+        client
+            .call_controller(controller_schedule_after, (logs.clone(), "a"))
+            .unwrap();
+        client
+            .call_controller(controller_search, (logs.clone(), "rust"))
+            .unwrap();
+        client
+            .call_controller(controller_scroll, (logs.clone(), "0"))
+            .unwrap();
+        client
+            .call_controller(controller_scroll, (logs.clone(), "1"))
+            .unwrap();
+        client.destroy();
+        runtime.advance(Duration::from_secs(1));
+
+        assert!(logs.lock().unwrap().is_empty());
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_timers_fired_in_spawn() {
+        let client = MistyClient::new(
+            MistyViewModelManager::builder()
+                .register(search_view_model)
+                .build(),
+            MistyStateManager::new(misty_states!(SearchState)),
+            MistyServiceManager::builder().build(),
+            InlineAsyncTaskAdapter::default(),
+        );
+        client.on_signal(|_| {});
+        let logs: Logs = Default::default();
+
+        client
+            .call_controller(controller_schedule_after, (logs.clone(), "a"))
+            .unwrap();
+        client
+            .call_controller(controller_search, (logs.clone(), "rust"))
+            .unwrap();
+        client
+            .call_controller(controller_scroll, (logs.clone(), "0"))
+            .unwrap();
+        client
+            .call_controller(controller_scroll, (logs.clone(), "1"))
+            .unwrap();
+        client.flush_scheduled_tasks().unwrap();
+
+        assert_eq!(logs.lock().unwrap().clone(), vec!["a", "rust", "0", "1"]);
+    }
+}
@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use misty_vm::async_task::IAsyncTaskRuntimeAdapter;
+    use misty_vm_test::runtime::DeterministicRuntime;
+
+    #[test]
+    fn test_timers_fire_in_deadline_order() {
+        let runtime = DeterministicRuntime::new();
+        let adapter = runtime.adapter();
+        let logs: Arc<Mutex<Vec<String>>> = Default::default();
+
+        for (name, millis) in [("c", 300), ("a", 100), ("b", 200), ("a2", 100)] {
+            let runtime_ = runtime.clone();
+            let logs = logs.clone();
+            adapter.spawn(Box::pin(async move {
+                runtime_.sleep(Duration::from_millis(millis)).await;
+                logs.lock()
+                    .unwrap()
+                    .push(format!("{} {:?}", name, runtime_.now()));
+            }));
+        }
+
+        runtime.run_until_idle();
+        assert!(logs.lock().unwrap().is_empty());
+        assert_eq!(runtime.running_tasks(), 4);
+
+        runtime.advance(Duration::from_millis(250));
+        assert_eq!(
+            logs.lock().unwrap().clone(),
+            vec!["a 100ms", "a2 100ms", "b 200ms"]
+        );
+        assert_eq!(runtime.now(), Duration::from_millis(250));
+
+        runtime.advance(Duration::from_millis(50));
+        assert_eq!(logs.lock().unwrap().last().unwrap(), "c 300ms");
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_abort() {
+        let runtime = DeterministicRuntime::new();
+        let adapter = runtime.adapter();
+        let logs: Arc<Mutex<Vec<i32>>> = Default::default();
+
+        let id = {
+            let runtime_ = runtime.clone();
+            let logs = logs.clone();
+            adapter.spawn(Box::pin(async move {
+                for i in 0.. {
+                    logs.lock().unwrap().push(i);
+                    runtime_.sleep(Duration::from_secs(1)).await;
+                }
+            }))
+        };
+
+        runtime.advance(Duration::from_millis(1500));
+        adapter.try_abort(id);
+        runtime.advance(Duration::from_secs(10));
+        assert_eq!(logs.lock().unwrap().clone(), vec![0, 1]);
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+}
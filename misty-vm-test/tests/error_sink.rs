@@ -0,0 +1,185 @@
+use std::convert::Infallible;
+
+use misty_vm::{
+    async_task::MistyAsyncTaskTrait, client::AsReadonlyMistyClientHandle,
+    controllers::MistyControllerContext, MistyAsyncTask, MistyState, MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct ErrorState {
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, MistyAsyncTask)]
+struct FailAsyncTask;
+
+fn controller_spawn_error(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
+    FailAsyncTask::spawn(
+        &ctx,
+        |_ctx| async move { Err::<(), String>("boom".to_string()) },
+    );
+    Ok(())
+}
+
+fn controller_spawn_panic(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
+    FailAsyncTask::spawn(&ctx, |_ctx| async move {
+        if true {
+            panic!("crash");
+        }
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn controller_spawn_schedule_error(
+    ctx: MistyControllerContext,
+    _arg: (),
+) -> Result<(), Infallible> {
+    FailAsyncTask::spawn(&ctx, |ctx| async move {
+        ctx.schedule(|_handle| Err::<(), String>("rejected".to_string()));
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn controller_schedule_panic(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
+    ctx.handle()
+        .readonly_handle()
+        .schedule(|_handle| -> Result<(), Infallible> { panic!("scheduled crash") });
+    Ok(())
+}
+
+fn error_view_model(state: &ErrorState, root: &mut RootViewModelState) {
+    root.errors = state.errors.clone();
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use misty_vm::{
+        client::MistyClient,
+        errors::{MistyErrorEvent, MistyErrorKind, MistyErrorSource},
+        misty_states,
+        services::MistyServiceManager,
+        states::{MistyStateManager, MistyStateTrait},
+        views::MistyViewModelManager,
+    };
+    use misty_vm_test::runtime::DeterministicRuntime;
+
+    use crate::{
+        controller_schedule_panic, controller_spawn_error, controller_spawn_panic,
+        controller_spawn_schedule_error, error_view_model, ErrorState, RootViewModelState,
+    };
+
+    fn build_client(
+        runtime: &DeterministicRuntime,
+    ) -> (
+        MistyClient<RootViewModelState>,
+        Arc<Mutex<Vec<MistyErrorEvent>>>,
+    ) {
+        let client = MistyClient::new(
+            MistyViewModelManager::builder()
+                .register(error_view_model)
+                .build(),
+            MistyStateManager::new(misty_states!(ErrorState)),
+            MistyServiceManager::builder().build(),
+            runtime.adapter(),
+        );
+        client.on_signal(|_| {});
+        let events: Arc<Mutex<Vec<MistyErrorEvent>>> = Default::default();
+        {
+            let events = events.clone();
+            client.on_error(move |event| {
+                events.lock().unwrap().push(event.clone());
+            });
+        }
+        (client, events)
+    }
+
+    #[test]
+    fn test_spawn_errors() {
+        let runtime = DeterministicRuntime::new();
+        let (client, events) = build_client(&runtime);
+
+        client.call_controller(controller_spawn_error, ()).unwrap();
+        client.call_controller(controller_spawn_panic, ()).unwrap();
+        runtime.run_until_idle();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].source, MistyErrorSource::Spawn);
+        assert_eq!(events[0].task_type, Some("error_sink::FailAsyncTask"));
+        assert_eq!(events[0].kind, MistyErrorKind::Error("boom".to_string()));
+        assert_eq!(events[1].kind, MistyErrorKind::Panic("crash".to_string()));
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_schedule_errors() {
+        let runtime = DeterministicRuntime::new();
+        let (client, events) = build_client(&runtime);
+
+        client
+            .call_controller(controller_spawn_schedule_error, ())
+            .unwrap();
+        runtime.run_until_idle();
+        client
+            .call_controller(controller_schedule_panic, ())
+            .unwrap();
+        client.flush_scheduled_tasks().unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].source, MistyErrorSource::Schedule);
+        assert_eq!(events[0].task_type, Some("error_sink::FailAsyncTask"));
+        assert_eq!(
+            events[0].kind,
+            MistyErrorKind::Error("rejected".to_string())
+        );
+        assert_eq!(events[1].source, MistyErrorSource::Schedule);
+        assert_eq!(events[1].task_type, None);
+        assert_eq!(
+            events[1].kind,
+            MistyErrorKind::Panic("scheduled crash".to_string())
+        );
+    }
+
+    #[test]
+    fn test_error_update() {
+        let runtime = DeterministicRuntime::new();
+        let (client, events) = build_client(&runtime);
+        client.on_error_update(|handle, event| {
+            if let MistyErrorKind::Error(message) = &event.kind {
+                ErrorState::update(handle, |state| {
+                    state.errors.push(message.clone());
+                });
+            } else {
+                panic!("updater crash");
+            }
+        });
+
+        client.call_controller(controller_spawn_error, ()).unwrap();
+        runtime.run_until_idle();
+        let ret = client.flush_scheduled_tasks().unwrap();
+        assert_eq!(
+            ret.changed_view.unwrap().errors,
+            Some(vec!["boom".to_string()])
+        );
+
+        // a panicking updater is reported, but not updated again
+        client
+            .call_controller(controller_schedule_panic, ())
+            .unwrap();
+        client.flush_scheduled_tasks().unwrap();
+        client.flush_scheduled_tasks().unwrap();
+        let ret = client.flush_scheduled_tasks().unwrap();
+        assert!(ret.changed_view.is_none());
+        assert_eq!(events.lock().unwrap().len(), 3);
+    }
+}
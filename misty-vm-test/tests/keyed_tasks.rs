@@ -0,0 +1,150 @@
+use std::convert::Infallible;
+
+use futures::channel::oneshot;
+use misty_vm::{
+    async_task::{MistyAsyncTaskTrait, MistyTaskHandle},
+    controllers::MistyControllerContext,
+    states::MistyStateTrait,
+    MistyAsyncTask, MistyState, MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct ThumbnailState {
+    pub loaded: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub loaded: Vec<String>,
+}
+
+#[derive(Debug, MistyAsyncTask)]
+struct LoadThumbnailAsyncTask;
+
+fn controller_load_thumbnail(
+    ctx: MistyControllerContext,
+    arg: (u32, String, oneshot::Receiver<()>),
+) -> Result<MistyTaskHandle, Infallible> {
+    let (item_id, label, rx) = arg;
+    let handle = LoadThumbnailAsyncTask::spawn_once_keyed(&ctx, item_id, move |ctx| async move {
+        let _ = rx.await;
+        ctx.schedule(move |handle| {
+            ThumbnailState::update(handle, |state| {
+                state.loaded.push(format!("{} {}", item_id, label));
+            });
+            Ok::<(), Infallible>(())
+        });
+        Ok::<(), Infallible>(())
+    });
+    Ok(handle)
+}
+
+fn controller_cancel_thumbnail(ctx: MistyControllerContext, arg: u32) -> Result<(), Infallible> {
+    LoadThumbnailAsyncTask::cancel_keyed(&ctx, arg);
+    Ok(())
+}
+
+fn controller_cancel_all_thumbnails(
+    ctx: MistyControllerContext,
+    _arg: (),
+) -> Result<(), Infallible> {
+    LoadThumbnailAsyncTask::cancel_all(&ctx);
+    Ok(())
+}
+
+fn thumbnail_view_model(state: &ThumbnailState, root: &mut RootViewModelState) {
+    root.loaded = state.loaded.clone();
+}
+
+#[cfg(test)]
+mod test {
+    use futures::channel::oneshot;
+    use misty_vm::{
+        misty_states, services::MistyServiceManager, states::MistyStateManager,
+        views::MistyViewModelManager,
+    };
+    use misty_vm_test::{runtime::DeterministicRuntime, TestApp, TestAppContainer};
+
+    use crate::{
+        controller_cancel_all_thumbnails, controller_cancel_thumbnail, controller_load_thumbnail,
+        thumbnail_view_model, RootViewModelState, ThumbnailState,
+    };
+
+    fn build_app(runtime: &DeterministicRuntime) -> TestApp<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(thumbnail_view_model)
+            .build();
+        let service_manager = MistyServiceManager::builder().build();
+        let state_manager = MistyStateManager::new(misty_states!(ThumbnailState));
+
+        TestApp::with_async_task_runtime(
+            view_manager,
+            service_manager,
+            state_manager,
+            TestAppContainer::new(),
+            runtime.adapter(),
+        )
+    }
+
+    #[test]
+    fn test_latest_wins_per_key() {
+        let runtime = DeterministicRuntime::new();
+        let app = build_app(&runtime);
+
+        let mut senders = vec![];
+        let mut handles = vec![];
+        for (item_id, label) in [(1, "a"), (2, "a"), (1, "b")] {
+            let (tx, rx) = oneshot::channel();
+            senders.push(tx);
+            handles.push(
+                app.app()
+                    .call_controller(controller_load_thumbnail, (item_id, label.to_string(), rx)),
+            );
+        }
+        runtime.run_until_idle();
+        assert!(handles[0].is_cancelled());
+        assert!(!handles[1].is_cancelled());
+        assert_eq!(handles[2].key(), Some("1"));
+        assert_eq!(runtime.running_tasks(), 2);
+
+        for tx in senders.into_iter() {
+            let _ = tx.send(());
+        }
+        runtime.run_until_idle();
+        app.app().flush_schedules();
+        assert_eq!(app.state().loaded, vec!["2 a", "1 b"]);
+    }
+
+    #[test]
+    fn test_cancel_keyed() {
+        let runtime = DeterministicRuntime::new();
+        let app = build_app(&runtime);
+
+        let (_tx1, rx1) = oneshot::channel();
+        let (_tx2, rx2) = oneshot::channel();
+        let one = app
+            .app()
+            .call_controller(controller_load_thumbnail, (1, "a".to_string(), rx1));
+        let two = app
+            .app()
+            .call_controller(controller_load_thumbnail, (2, "a".to_string(), rx2));
+
+        app.app().call_controller(controller_cancel_thumbnail, 2);
+        assert!(!one.is_cancelled());
+        assert!(two.is_cancelled());
+
+        // cancelling a key without a task does nothing
+        app.app().call_controller(controller_cancel_thumbnail, 2);
+        let (_tx3, rx3) = oneshot::channel();
+        let three = app
+            .app()
+            .call_controller(controller_load_thumbnail, (2, "b".to_string(), rx3));
+        assert!(!three.is_cancelled());
+
+        app.app()
+            .call_controller(controller_cancel_all_thumbnails, ());
+        assert!(one.is_cancelled());
+        assert!(three.is_cancelled());
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+}
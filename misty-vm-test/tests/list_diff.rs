@@ -0,0 +1,74 @@
+use misty_vm::views::{apply_list_diff, diff_keyed_list, ListDiffOp};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    pub id: i32,
+    pub title: &'static str,
+}
+
+fn item(id: i32, title: &'static str) -> Item {
+    Item { id, title }
+}
+
+fn diff_and_apply(prev: Vec<Item>, next: Vec<Item>) -> Vec<ListDiffOp<Item>> {
+    let ops = diff_keyed_list(&prev, &next, |item| item.id);
+    let mut list = prev;
+    apply_list_diff(&mut list, ops.clone());
+    assert_eq!(list, next);
+    ops
+}
+
+#[cfg(test)]
+mod test {
+    use misty_vm::views::ListDiffOp;
+
+    use crate::{diff_and_apply, item};
+
+    #[test]
+    fn test_unchanged() {
+        let list = vec![item(1, "a"), item(2, "b")];
+        let ops = diff_and_apply(list.clone(), list);
+        assert!(ops.is_empty());
+    }
+
+    #[test]
+    fn test_insert_remove_update() {
+        let ops = diff_and_apply(
+            vec![item(1, "a"), item(2, "b"), item(3, "c")],
+            vec![item(1, "a"), item(3, "C"), item(4, "d")],
+        );
+        assert_eq!(
+            ops,
+            vec![
+                ListDiffOp::Remove { index: 1 },
+                ListDiffOp::Update {
+                    index: 1,
+                    item: item(3, "C")
+                },
+                ListDiffOp::Insert {
+                    index: 2,
+                    item: item(4, "d")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_move() {
+        let ops = diff_and_apply(
+            vec![item(1, "a"), item(2, "b"), item(3, "c")],
+            vec![item(3, "c"), item(1, "a"), item(2, "b")],
+        );
+        assert_eq!(ops, vec![ListDiffOp::Move { from: 2, to: 0 }]);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        diff_and_apply(
+            vec![item(1, "a"), item(2, "b"), item(3, "c"), item(4, "d")],
+            vec![item(5, "e"), item(4, "D"), item(2, "b"), item(1, "a")],
+        );
+        diff_and_apply(vec![], vec![item(1, "a"), item(2, "b")]);
+        diff_and_apply(vec![item(1, "a"), item(2, "b")], vec![]);
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use misty_vm::{
+    client::AsReadonlyMistyClientHandle,
+    controllers::MistyControllerContext,
+    middlewares::{MistyControllerCall, MistyControllerCallEnd, MistyMiddleware},
+    states::MistyStateTrait,
+    MistyView,
+};
+use misty_vm_test::fixtures::CounterState;
+use serde::Serialize;
+
+#[derive(Debug, Default, Clone, MistyView)]
+#[misty(patch_derive(Serialize))]
+struct RootViewModelState {
+    pub count: i32,
+}
+
+fn controller_add(ctx: MistyControllerContext, arg: i32) -> Result<(), String> {
+    if arg < 0 {
+        return Err("negative".to_string());
+    }
+    CounterState::update(&ctx, |state| {
+        state.count += arg;
+    });
+    Ok(())
+}
+
+fn controller_add_text(ctx: MistyControllerContext, arg: &str) -> Result<(), String> {
+    let arg: i32 = arg.parse().map_err(|_| format!("not a number: {}", arg))?;
+    controller_add(ctx, arg)
+}
+
+fn controller_schedule_add(ctx: MistyControllerContext, arg: i32) -> Result<(), Infallible> {
+    ctx.handle().readonly_handle().schedule(move |handle| {
+        CounterState::update(handle, |state| {
+            state.count += arg;
+        });
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn counter_view_model(state: &CounterState, root: &mut RootViewModelState) {
+    root.count = state.count;
+}
+
+#[derive(Default, Clone)]
+struct LogMiddleware {
+    logs: Arc<Mutex<Vec<String>>>,
+}
+
+impl MistyMiddleware for LogMiddleware {
+    fn before_call(&self, call: &MistyControllerCall) -> Result<(), String> {
+        let name = call.name.rsplit("::").next().unwrap();
+        let log = match call.arg {
+            None => format!("call {} {}", name, call.arg_type),
+            Some(arg) => format!("call {} {:?}", name, arg),
+        };
+        self.logs.lock().unwrap().push(log);
+        Ok(())
+    }
+
+    fn after_call(&self, call: &MistyControllerCallEnd) {
+        let name = call.name.rsplit("::").next().unwrap();
+        let count = call
+            .changed_view
+            .and_then(|patch| patch.downcast_ref::<RootViewModelStatePatch>())
+            .and_then(|patch| patch.count);
+        let log = match (call.error_type, call.error, call.value) {
+            (None, _, None) => format!("ok {} {:?} {:?}", name, call.seq, count),
+            (None, _, Some(value)) => format!("ok {} {:?} {:?} {:?}", name, call.seq, count, value),
+            (Some(error_type), None, _) => format!("err {} {}", name, error_type),
+            (Some(_), Some(err), _) => format!("err {} {:?}", name, err),
+        };
+        self.logs.lock().unwrap().push(log);
+    }
+}
+
+#[derive(Default, Clone)]
+struct LockMiddleware {
+    locked: Arc<AtomicBool>,
+}
+
+impl MistyMiddleware for LockMiddleware {
+    fn before_call(&self, _call: &MistyControllerCall) -> Result<(), String> {
+        if self.locked.load(Ordering::SeqCst) {
+            return Err("app is locked".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::Ordering;
+
+    use misty_vm::{
+        client::MistyClient, codecs::MistyJsonCodec, controllers::MistyControllerError,
+        misty_states, registry::MistyControllerRegistry, states::MistyStateManager,
+        views::MistyViewModelManager,
+    };
+    use misty_vm_test::fixtures::new_client;
+
+    use crate::{
+        controller_add, controller_add_text, controller_schedule_add, counter_view_model,
+        CounterState, LockMiddleware, LogMiddleware, RootViewModelState,
+    };
+
+    fn build_client() -> MistyClient<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(counter_view_model)
+            .build();
+        let state_manager = MistyStateManager::new(misty_states!(CounterState));
+        let client = new_client(view_manager, state_manager);
+        client.on_signal(|_| {});
+        client
+    }
+
+    fn build_registry_client() -> MistyClient<RootViewModelState> {
+        let client = build_client();
+        client.set_controller_registry(
+            MistyControllerRegistry::builder(MistyJsonCodec)
+                .register("add", controller_add)
+                .build(),
+        );
+        client
+    }
+
+    #[test]
+    fn test_log_middleware() {
+        let client = build_client();
+        let middleware = LogMiddleware::default();
+        client.add_middleware(middleware.clone());
+
+        client.call_controller(controller_add, 1).unwrap();
+        let _ = client.call_controller(controller_add, -1);
+        client.call_controller(controller_schedule_add, 2).unwrap();
+        client.flush_scheduled_tasks().unwrap();
+
+        let logs = middleware.logs.lock().unwrap().clone();
+        assert_eq!(
+            logs,
+            vec![
+                "call controller_add i32",
+                "ok controller_add Some(1) Some(1)",
+                "call controller_add i32",
+                "err controller_add alloc::string::String",
+                "call controller_schedule_add i32",
+                "ok controller_schedule_add Some(2) None",
+                "call controller_flush_scheduled_tasks ()",
+                "ok controller_flush_scheduled_tasks Some(3) Some(3)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_log_registry_calls() {
+        let client = build_registry_client();
+        let middleware = LogMiddleware::default();
+        client.add_middleware(middleware.clone());
+
+        client.call_controller_by_name("add", b"2");
+        client.call_controller_by_name("add", b"-1");
+
+        let logs = middleware.logs.lock().unwrap().clone();
+        assert_eq!(
+            logs,
+            vec![
+                "call controller_add 2",
+                "ok controller_add Some(1) Some(2) ()",
+                "call controller_add -1",
+                "err controller_add \"negative\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_borrowed_arg() {
+        let client = build_client();
+        let middleware = LogMiddleware::default();
+        client.add_middleware(middleware.clone());
+
+        let text = String::from("2");
+        let ret = client
+            .call_controller(controller_add_text, text.as_str())
+            .unwrap();
+        assert_eq!(ret.changed_view.unwrap().count, Some(2));
+
+        let logs = middleware.logs.lock().unwrap().clone();
+        assert_eq!(
+            logs,
+            vec![
+                "call controller_add_text &str",
+                "ok controller_add_text Some(1) Some(2)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reject_by_middleware() {
+        let client = build_client();
+        let middleware = LockMiddleware::default();
+        client.add_middleware(middleware.clone());
+
+        client.call_controller(controller_add, 1).unwrap();
+        middleware.locked.store(true, Ordering::SeqCst);
+        let ret = client.call_controller(controller_add, 1);
+        assert!(
+            matches!(ret, Err(MistyControllerError::Rejected(reason)) if reason == "app is locked")
+        );
+
+        middleware.locked.store(false, Ordering::SeqCst);
+        let ret = client.call_controller(controller_add, 1).unwrap();
+        assert_eq!(ret.changed_view.unwrap().count, Some(2));
+    }
+}
@@ -0,0 +1,71 @@
+use std::convert::Infallible;
+
+use misty_vm::{controllers::MistyControllerContext, states::MistyStateTrait, MistyView};
+use misty_vm_test::fixtures::CounterState;
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub count: i32,
+}
+
+fn controller_add(ctx: MistyControllerContext, arg: i32) -> Result<i32, Infallible> {
+    let count = CounterState::update(&ctx, |state| {
+        state.count += arg;
+        state.count
+    });
+    Ok(count)
+}
+
+fn counter_view_model(state: &CounterState, root: &mut RootViewModelState) {
+    root.count = state.count;
+}
+
+#[cfg(test)]
+mod test {
+    use misty_vm::{
+        client::MistyClient, misty_states, states::MistyStateManager, views::MistyViewModelManager,
+    };
+    use misty_vm_test::fixtures::new_client;
+
+    use crate::{controller_add, counter_view_model, CounterState, RootViewModelState};
+
+    fn build_client() -> MistyClient<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(counter_view_model)
+            .build();
+        let state_manager = MistyStateManager::new(misty_states!(CounterState));
+        new_client(view_manager, state_manager)
+    }
+
+    #[test]
+    fn test_isolated_clients() {
+        let a = build_client();
+        let b = build_client();
+        assert_ne!(a.id(), b.id());
+
+        let ret = a.call_controller(controller_add, 1).unwrap();
+        assert_eq!(ret.changed_view.unwrap().count, Some(1));
+        let ret = b.call_controller(controller_add, 10).unwrap();
+        assert_eq!(ret.changed_view.unwrap().count, Some(10));
+        let ret = a.call_controller(controller_add, 2).unwrap();
+        assert_eq!(ret.value, 3);
+        assert_eq!(ret.changed_view.unwrap().count, Some(3));
+    }
+
+    #[test]
+    fn test_destroy_independently() {
+        let a = build_client();
+        let b = build_client();
+        let accessor = a.accessor();
+
+        a.destroy();
+        assert!(a.is_destroyed());
+        assert!(!b.is_destroyed());
+
+        drop(a);
+        assert!(accessor.get().is_none());
+
+        let ret = b.call_controller(controller_add, 5).unwrap();
+        assert_eq!(ret.changed_view.unwrap().count, Some(5));
+    }
+}
@@ -0,0 +1,166 @@
+use std::convert::Infallible;
+
+use misty_vm::{
+    controllers::MistyControllerContext, states::MistyStateTrait, MistyState, MistyView,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, MistyState)]
+#[misty(persist)]
+struct DraftState {
+    pub text: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, MistyState)]
+#[misty(persist = "settings.v1")]
+struct SettingsState {
+    pub dark: bool,
+}
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct CursorState {
+    pub pos: usize,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub text: String,
+    pub dark: bool,
+    pub pos: usize,
+}
+
+fn controller_type(ctx: MistyControllerContext, arg: String) -> Result<(), Infallible> {
+    DraftState::update(&ctx, |state| {
+        state.text.push_str(&arg);
+    });
+    CursorState::update(&ctx, |state| {
+        state.pos += arg.len();
+    });
+    Ok(())
+}
+
+fn controller_toggle_dark(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
+    SettingsState::update(&ctx, |state| {
+        state.dark = !state.dark;
+    });
+    Ok(())
+}
+
+fn editor_view_model(
+    (draft, settings, cursor): (&DraftState, &SettingsState, &CursorState),
+    root: &mut RootViewModelState,
+) {
+    root.text = draft.text.clone();
+    root.dark = settings.dark;
+    root.pos = cursor.pos;
+}
+
+#[cfg(test)]
+mod test {
+    use misty_vm::{
+        client::MistyClient,
+        controllers::MistyControllerError,
+        misty_states,
+        states::{MistyStateManager, MistyStateSnapshotError},
+        views::MistyViewModelManager,
+    };
+    use misty_vm_test::fixtures::new_client;
+
+    use crate::{
+        controller_toggle_dark, controller_type, editor_view_model, CursorState, DraftState,
+        RootViewModelState, SettingsState,
+    };
+
+    fn build_state_manager() -> MistyStateManager {
+        MistyStateManager::new(misty_states!(DraftState, SettingsState, CursorState))
+    }
+
+    fn build_client(state_manager: MistyStateManager) -> MistyClient<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(editor_view_model)
+            .build();
+        new_client(view_manager, state_manager)
+    }
+
+    fn build_snapshot() -> Vec<u8> {
+        let client = build_client(build_state_manager());
+        client
+            .call_controller(controller_type, "hello".to_string())
+            .unwrap();
+        client.call_controller(controller_toggle_dark, ()).unwrap();
+        client.snapshot().unwrap()
+    }
+
+    #[test]
+    fn test_restore_client() {
+        let snapshot = build_snapshot();
+
+        let client = build_client(build_state_manager());
+        let view = client.restore(&snapshot).unwrap().changed_view.unwrap();
+        assert_eq!(view.text.as_deref(), Some("hello"));
+        assert_eq!(view.dark, Some(true));
+        assert_eq!(view.pos, None);
+    }
+
+    #[test]
+    fn test_restore_state_manager() {
+        let snapshot = build_snapshot();
+
+        let state_manager = build_state_manager();
+        state_manager.restore(&snapshot).unwrap();
+        let client = build_client(state_manager);
+        let view = client
+            .call_controller(controller_type, " world".to_string())
+            .unwrap()
+            .changed_view
+            .unwrap();
+        assert_eq!(view.text.as_deref(), Some("hello world"));
+        assert_eq!(view.pos, Some(6));
+    }
+
+    #[test]
+    fn test_snapshot_keys() {
+        let snapshot = build_snapshot();
+        let value: serde_json::Value = serde_json::from_slice(&snapshot).unwrap();
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["states"]["DraftState"]["text"], "hello");
+        assert_eq!(value["states"]["settings.v1"]["dark"], true);
+        assert!(value["states"].get("CursorState").is_none());
+    }
+
+    #[test]
+    fn test_restore_unsupported_version() {
+        let client = build_client(build_state_manager());
+        let ret = client.restore(br#"{"version":100,"states":{}}"#);
+        assert!(matches!(
+            ret,
+            Err(MistyControllerError::Failed(
+                MistyStateSnapshotError::UnsupportedVersion(100)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_restore_invalid_keeps_states() {
+        let client = build_client(build_state_manager());
+        client
+            .call_controller(controller_type, "draft".to_string())
+            .unwrap();
+        let ret = client.restore(
+            br#"{"version":1,"states":{"DraftState":{"text":"x"},"settings.v1":{"dark":1}}}"#,
+        );
+        assert!(matches!(
+            ret,
+            Err(MistyControllerError::Failed(
+                MistyStateSnapshotError::Codec(_)
+            ))
+        ));
+
+        let view = client
+            .call_controller(controller_type, "!".to_string())
+            .unwrap()
+            .changed_view
+            .unwrap();
+        assert_eq!(view.text.as_deref(), Some("draft!"));
+    }
+}
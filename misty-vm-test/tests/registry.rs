@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use misty_vm::{controllers::MistyControllerContext, states::MistyStateTrait, MistyView};
+use misty_vm_test::fixtures::CounterState;
+
+#[derive(Debug, Default, Clone, MistyView)]
+#[misty(patch_derive(Serialize))]
+struct RootViewModelState {
+    pub count: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddArg {
+    value: i32,
+}
+
+fn controller_add(ctx: MistyControllerContext, arg: AddArg) -> Result<i32, String> {
+    if arg.value < 0 {
+        return Err("negative".to_string());
+    }
+    let count = CounterState::update(&ctx, |state| {
+        state.count += arg.value;
+        state.count
+    });
+    Ok(count)
+}
+
+fn counter_view_model(state: &CounterState, root: &mut RootViewModelState) {
+    root.count = state.count;
+}
+
+#[cfg(test)]
+mod test {
+    use misty_vm::{
+        client::MistyClient, codecs::MistyJsonCodec, misty_states,
+        registry::MistyControllerRegistry, states::MistyStateManager, views::MistyViewModelManager,
+    };
+    use misty_vm_test::fixtures::new_client;
+    use serde_json::json;
+
+    use crate::{controller_add, counter_view_model, CounterState, RootViewModelState};
+
+    fn build_client() -> MistyClient<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(counter_view_model)
+            .build();
+        let state_manager = MistyStateManager::new(misty_states!(CounterState));
+        let client = new_client(view_manager, state_manager);
+        client.on_signal(|_| {});
+        client.set_controller_registry(
+            MistyControllerRegistry::builder(MistyJsonCodec)
+                .register("add", controller_add)
+                .build(),
+        );
+        client
+    }
+
+    fn call(
+        client: &MistyClient<RootViewModelState>,
+        name: &str,
+        arg: serde_json::Value,
+    ) -> serde_json::Value {
+        let arg = serde_json::to_vec(&arg).unwrap();
+        let ret = client.call_controller_by_name(name, &arg);
+        serde_json::from_slice(&ret).unwrap()
+    }
+
+    #[test]
+    fn test_call_controller_by_name() {
+        let client = build_client();
+        let ret = call(&client, "add", json!({ "value": 2 }));
+        assert_eq!(ret["result"]["Ok"]["changed_view"], json!({ "count": 2 }));
+        assert_eq!(ret["result"]["Ok"]["value"], json!(2));
+
+        let ret = call(&client, "add", json!({ "value": 0 }));
+        assert_eq!(ret["result"]["Ok"]["changed_view"], json!(null));
+        assert_eq!(ret["result"]["Ok"]["value"], json!(2));
+    }
+
+    #[test]
+    fn test_dispatch_errors() {
+        let client = build_client();
+        let ret = call(&client, "sub", json!({ "value": 1 }));
+        assert_eq!(
+            ret["result"]["Err"],
+            json!({ "kind": "UnknownController", "message": "unknown controller sub" })
+        );
+
+        let ret = call(&client, "add", json!({ "count": 1 }));
+        assert_eq!(ret["result"]["Err"]["kind"], json!("Codec"));
+
+        let ret = call(&client, "add", json!({ "value": -1 }));
+        assert_eq!(
+            ret["result"]["Err"],
+            json!({ "kind": "Failed", "message": "negative" })
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_register_twice() {
+        let _ = MistyControllerRegistry::<RootViewModelState>::builder(MistyJsonCodec)
+            .register("add", controller_add)
+            .register("add", controller_add);
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use misty_vm::{
+    async_task::MistyAsyncTaskTrait, controllers::MistyControllerContext, retry::MistyRetryPolicy,
+    MistyAsyncTask, MistyState, MistyView,
+};
+use misty_vm_test::runtime::DeterministicRuntime;
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct FetchState {}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {}
+
+#[derive(Debug, MistyAsyncTask)]
+struct FetchAsyncTask;
+
+/// Times of the attempts, and the errors returned by them in order. Attempts after the last
+/// error succeed.
+#[derive(Clone)]
+struct FakeFetch {
+    runtime: DeterministicRuntime,
+    attempts: Arc<Mutex<Vec<Duration>>>,
+    errors: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl FakeFetch {
+    fn new(runtime: &DeterministicRuntime, errors: Vec<&'static str>) -> Self {
+        Self {
+            runtime: runtime.clone(),
+            attempts: Default::default(),
+            errors: Arc::new(Mutex::new(errors)),
+        }
+    }
+
+    fn fetch(&self) -> Result<(), String> {
+        self.attempts.lock().unwrap().push(self.runtime.now());
+        let mut errors = self.errors.lock().unwrap();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(errors.remove(0).to_string())
+    }
+
+    fn attempts(&self) -> Vec<u64> {
+        let attempts = self.attempts.lock().unwrap();
+        attempts.iter().map(|t| t.as_millis() as u64).collect()
+    }
+}
+
+fn controller_fetch(
+    ctx: MistyControllerContext,
+    arg: (FakeFetch, MistyRetryPolicy<String>),
+) -> Result<(), Infallible> {
+    let (fake, policy) = arg;
+    FetchAsyncTask::spawn_once_with_retry(&ctx, policy, move |_ctx| {
+        let fake = fake.clone();
+        async move { fake.fetch() }
+    });
+    Ok(())
+}
+
+fn controller_cancel_fetch(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
+    FetchAsyncTask::cancel_all(&ctx);
+    Ok(())
+}
+
+fn fetch_view_model(_state: &FetchState, _root: &mut RootViewModelState) {}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use misty_vm::{
+        client::MistyClient, errors::MistyErrorKind, misty_states, retry::MistyRetryPolicy,
+        services::MistyServiceManager, states::MistyStateManager, views::MistyViewModelManager,
+    };
+    use misty_vm_test::runtime::DeterministicRuntime;
+
+    use crate::{
+        controller_cancel_fetch, controller_fetch, fetch_view_model, FakeFetch, FetchState,
+        RootViewModelState,
+    };
+
+    fn build_client(
+        runtime: &DeterministicRuntime,
+    ) -> (
+        MistyClient<RootViewModelState>,
+        Arc<Mutex<Vec<MistyErrorKind>>>,
+    ) {
+        let client = MistyClient::new(
+            MistyViewModelManager::builder()
+                .register(fetch_view_model)
+                .build(),
+            MistyStateManager::new(misty_states!(FetchState)),
+            MistyServiceManager::builder().build(),
+            runtime.adapter(),
+        );
+        let errors: Arc<Mutex<Vec<MistyErrorKind>>> = Default::default();
+        {
+            let errors = errors.clone();
+            client.on_error(move |event| {
+                errors.lock().unwrap().push(event.kind.clone());
+            });
+        }
+        (client, errors)
+    }
+
+    fn policy(max_attempts: u32) -> MistyRetryPolicy<String> {
+        MistyRetryPolicy::new(max_attempts)
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(250))
+            .with_jitter(0.0)
+    }
+
+    #[test]
+    fn test_backoff() {
+        let runtime = DeterministicRuntime::new();
+        let (client, errors) = build_client(&runtime);
+        let fake = FakeFetch::new(&runtime, vec!["timeout", "timeout", "timeout"]);
+
+        client
+            .call_controller(controller_fetch, (fake.clone(), policy(5)))
+            .unwrap();
+        runtime.advance(Duration::from_secs(10));
+
+        assert_eq!(fake.attempts(), vec![0, 100, 300, 550]);
+        assert!(errors.lock().unwrap().is_empty());
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_give_up() {
+        let runtime = DeterministicRuntime::new();
+        let (client, errors) = build_client(&runtime);
+
+        let fake = FakeFetch::new(&runtime, vec!["timeout"; 5]);
+        client
+            .call_controller(controller_fetch, (fake.clone(), policy(3)))
+            .unwrap();
+        runtime.advance(Duration::from_secs(10));
+        assert_eq!(fake.attempts(), vec![0, 100, 300]);
+
+        // errors not matching the predicate are not retried
+        let fake = FakeFetch::new(&runtime, vec!["timeout", "not found", "timeout"]);
+        let policy = policy(3).retry_if(|err: &String| err == "timeout");
+        client
+            .call_controller(controller_fetch, (fake.clone(), policy))
+            .unwrap();
+        runtime.advance(Duration::from_secs(10));
+        assert_eq!(fake.attempts(), vec![10000, 10100]);
+
+        assert_eq!(
+            errors.lock().unwrap().clone(),
+            vec![
+                MistyErrorKind::Error("timeout".to_string()),
+                MistyErrorKind::Error("not found".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cancel_pending_retry() {
+        let runtime = DeterministicRuntime::new();
+        let (client, errors) = build_client(&runtime);
+        let fake = FakeFetch::new(&runtime, vec!["timeout"; 5]);
+
+        client
+            .call_controller(controller_fetch, (fake.clone(), policy(5)))
+            .unwrap();
+        runtime.advance(Duration::from_millis(150));
+        client.call_controller(controller_cancel_fetch, ()).unwrap();
+        runtime.advance(Duration::from_secs(10));
+
+        assert_eq!(fake.attempts(), vec![0, 100]);
+        assert!(errors.lock().unwrap().is_empty());
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_jitter() {
+        let policy = MistyRetryPolicy::<String>::new(10)
+            .with_backoff(Duration::from_secs(1), Duration::from_secs(4))
+            .with_jitter(0.5);
+        for _ in 0..100 {
+            let first = policy.backoff(1);
+            assert!(first > Duration::from_millis(500) && first <= Duration::from_secs(1));
+            let capped = policy.backoff(8);
+            assert!(capped > Duration::from_secs(2) && capped <= Duration::from_secs(4));
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::convert::Infallible;
+
+use misty_vm::{
+    client::AsReadonlyMistyClientHandle,
+    controllers::MistyControllerContext,
+    resources::MistyResourceHandle,
+    states::{MistyComputedTrait, MistyStateTrait},
+    MistyState, MistyView,
+};
+use misty_vm_test::fixtures::CounterState;
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct LogState {
+    pub logs: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct ImageState {
+    pub image: Option<MistyResourceHandle>,
+}
+
+#[derive(Debug, Default, Clone, MistyState)]
+#[misty(computed)]
+struct DoubleState {
+    pub count: i32,
+}
+
+impl MistyComputedTrait for DoubleState {
+    type Deps<'a> = &'a CounterState;
+
+    fn compute(counter: Self::Deps<'_>) -> Self {
+        Self {
+            count: counter.count * 2,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub count: i32,
+}
+
+fn controller_add(ctx: MistyControllerContext, arg: i32) -> Result<(), String> {
+    CounterState::update(&ctx, |state| {
+        state.count += arg;
+    });
+    LogState::update(&ctx, |state| {
+        state.logs.push(format!("add {}", arg));
+    });
+    if arg < 0 {
+        return Err("negative".to_string());
+    }
+    Ok(())
+}
+
+fn controller_add_partial(ctx: MistyControllerContext, arg: i32) -> Result<(), String> {
+    ctx.allow_partial_commit();
+    controller_add(ctx, arg)
+}
+
+fn controller_set_image(ctx: MistyControllerContext, buf: Vec<u8>) -> Result<(), String> {
+    let handle = ctx.handle().resource_manager().insert(buf);
+    ImageState::update(&ctx, |state| {
+        state.image = Some(handle);
+    });
+    if ImageState::map(&ctx, |state| {
+        state.image.as_ref().unwrap().load().is_empty()
+    }) {
+        return Err("empty image".to_string());
+    }
+    Ok(())
+}
+
+fn controller_add_panic(ctx: MistyControllerContext, arg: i32) -> Result<(), Infallible> {
+    CounterState::update(&ctx, |state| {
+        state.count += arg;
+    });
+    panic!("generate panic!");
+}
+
+fn controller_add_partial_panic(ctx: MistyControllerContext, arg: i32) -> Result<(), Infallible> {
+    ctx.allow_partial_commit();
+    controller_add_panic(ctx, arg)
+}
+
+fn controller_schedule_adds(ctx: MistyControllerContext, args: Vec<i32>) -> Result<(), Infallible> {
+    for arg in args.into_iter() {
+        ctx.handle().readonly_handle().schedule(move |handle| {
+            CounterState::update(handle, |state| {
+                state.count += arg;
+            });
+            if arg == 0 {
+                panic!("generate panic in schedule!");
+            }
+            if arg < 0 {
+                return Err("negative".to_string());
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+fn counter_view_model(state: &CounterState, root: &mut RootViewModelState) {
+    root.count = state.count;
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use misty_vm::{
+        client::MistyClient,
+        misty_states,
+        resources::ResourceUpdateAction,
+        signals::MistySignal,
+        states::{MistyStateManager, MistyStateTrait},
+        views::{MistyViewModelManager, MistyViewTrait},
+    };
+    use misty_vm_test::fixtures::new_client;
+
+    use crate::{
+        controller_add, controller_add_panic, controller_add_partial, controller_add_partial_panic,
+        controller_schedule_adds, controller_set_image, counter_view_model, CounterState,
+        DoubleState, ImageState, LogState, RootViewModelState,
+    };
+
+    fn build_client() -> MistyClient<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(counter_view_model)
+            .build();
+        let state_manager = MistyStateManager::new(misty_states!(
+            CounterState,
+            LogState,
+            ImageState,
+            DoubleState
+        ));
+        let client = new_client(view_manager, state_manager);
+        client.on_signal(|_| {});
+        client
+    }
+
+    fn watch_schedule(client: &MistyClient<RootViewModelState>) -> Arc<AtomicBool> {
+        let scheduled = Arc::new(AtomicBool::new(false));
+        {
+            let scheduled = scheduled.clone();
+            client.on_signal(move |signal| match signal {
+                MistySignal::Schedule => scheduled.store(true, Ordering::SeqCst),
+            });
+        }
+        scheduled
+    }
+
+    fn count(client: &MistyClient<RootViewModelState>) -> i32 {
+        client
+            .accessor()
+            .get()
+            .map(|pod| CounterState::map(pod.handle(), |state| state.count))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rollback_on_err() {
+        let client = build_client();
+        client.call_controller(controller_add, 2).unwrap();
+
+        let ret = client.call_controller(controller_add, -1);
+        assert!(ret.is_err());
+        assert_eq!(count(&client), 2);
+    }
+
+    #[test]
+    fn test_rollback_on_panic() {
+        std::env::set_var("RUST_BACKTRACE", "0");
+        let client = build_client();
+        client.call_controller(controller_add, 2).unwrap();
+
+        let ret = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            client.call_controller(controller_add_panic, 3)
+        }));
+        assert!(ret.is_err());
+        assert_eq!(count(&client), 2);
+    }
+
+    #[test]
+    fn test_partial_commit() {
+        let client = build_client();
+        client.call_controller(controller_add, 2).unwrap();
+
+        let ret = client.call_controller(controller_add_partial, -1);
+        assert!(ret.is_err());
+        assert_eq!(count(&client), 1);
+    }
+
+    #[test]
+    fn test_partial_commit_reaches_host_view() {
+        let client = build_client();
+        let scheduled = watch_schedule(&client);
+        let mut host_view = RootViewModelState::default();
+        let ret = client.call_controller(controller_add, 2).unwrap();
+        host_view.apply_patch(ret.changed_view.unwrap());
+
+        let ret = client.call_controller(controller_add_partial, -1);
+        assert!(ret.is_err());
+        assert!(scheduled.load(Ordering::SeqCst));
+        let ret = client.flush_scheduled_tasks().unwrap();
+        host_view.apply_patch(ret.changed_view.unwrap());
+        assert_eq!(host_view.count, 1);
+        assert_eq!(count(&client), 1);
+    }
+
+    #[test]
+    fn test_rollback_does_not_schedule() {
+        let client = build_client();
+        let scheduled = watch_schedule(&client);
+        client.call_controller(controller_add, 2).unwrap();
+
+        let ret = client.call_controller(controller_add, -1);
+        assert!(ret.is_err());
+        assert!(!scheduled.load(Ordering::SeqCst));
+        let double = client
+            .accessor()
+            .get()
+            .map(|pod| DoubleState::map(pod.handle(), |state| state.count))
+            .unwrap();
+        assert_eq!(double, 4);
+    }
+
+    #[test]
+    fn test_partial_commit_on_panic_schedules() {
+        std::env::set_var("RUST_BACKTRACE", "0");
+        let client = build_client();
+        let scheduled = watch_schedule(&client);
+
+        let ret = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            client.call_controller(controller_add_partial_panic, 3)
+        }));
+        assert!(ret.is_err());
+        assert!(scheduled.load(Ordering::SeqCst));
+        let ret = client.flush_scheduled_tasks().unwrap();
+        assert_eq!(ret.changed_view.unwrap().count, Some(3));
+    }
+
+    #[test]
+    fn test_rollback_discards_resources() {
+        let client = build_client();
+        let ret = client.call_controller(controller_set_image, vec![]);
+        assert!(ret.is_err());
+
+        let ret = client.call_controller(controller_add, 1).unwrap();
+        assert!(ret.changed_resources.is_empty());
+
+        let ret = client
+            .call_controller(controller_set_image, vec![1])
+            .unwrap();
+        assert!(matches!(
+            ret.changed_resources.as_slice(),
+            [ResourceUpdateAction::Insert(_, buf)] if buf == &vec![1]
+        ));
+    }
+
+    #[test]
+    fn test_rollback_failed_scheduled_task_only() {
+        std::env::set_var("RUST_BACKTRACE", "0");
+        let client = build_client();
+        client
+            .call_controller(controller_schedule_adds, vec![1, -10, 0, 100])
+            .unwrap();
+
+        let ret = client.flush_scheduled_tasks().unwrap();
+        assert_eq!(ret.changed_view.unwrap().count, Some(101));
+    }
+
+    #[test]
+    fn test_rollback_keeps_other_states() {
+        let client = build_client();
+        client.call_controller(controller_add, 1).unwrap();
+        let _ = client.call_controller(controller_add, -1);
+        let _ = client.call_controller(controller_add_partial, -2);
+
+        let logs = client
+            .accessor()
+            .get()
+            .map(|pod| LogState::map(pod.handle(), |state| state.logs.clone()))
+            .unwrap();
+        assert_eq!(logs, vec!["add 1".to_string(), "add -2".to_string()]);
+    }
+}
@@ -0,0 +1,179 @@
+use std::convert::Infallible;
+
+use misty_vm::{
+    async_task::MistyAsyncTaskTrait, controllers::MistyControllerContext, states::MistyStateTrait,
+    MistyAsyncTask, MistyState, MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct CounterState {
+    pub count: i32,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub count: i32,
+}
+
+#[derive(Debug, MistyAsyncTask)]
+struct AddLocalAsyncTask;
+
+fn controller_add_local(ctx: MistyControllerContext, arg: i32) -> Result<(), Infallible> {
+    AddLocalAsyncTask::spawn_local(&ctx, move |ctx| async move {
+        // a local task may hold values that are not Send across awaits
+        let arg = std::rc::Rc::new(arg);
+        tokio::task::yield_now().await;
+        let arg = *arg;
+        ctx.schedule(move |handle| {
+            CounterState::update(handle, |state| {
+                state.count += arg;
+            });
+            Ok::<(), Infallible>(())
+        });
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn counter_view_model(state: &CounterState, root: &mut RootViewModelState) {
+    root.count = state.count;
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use futures::{
+        channel::oneshot,
+        executor::{LocalPool, ThreadPool},
+        StreamExt,
+    };
+    use misty_vm::{
+        async_task::IAsyncTaskRuntimeAdapter,
+        client::MistyClient,
+        misty_states,
+        runtimes::{MistyFuturesAsyncTaskAdapter, MistyTokioAsyncTaskAdapter},
+        services::MistyServiceManager,
+        states::MistyStateManager,
+        views::MistyViewModelManager,
+    };
+
+    use crate::{controller_add_local, counter_view_model, CounterState};
+
+    #[tokio::test]
+    async fn test_tokio_cleanup_finished_tasks() {
+        let adapter = MistyTokioAsyncTaskAdapter::new();
+        let (tx, rx) = oneshot::channel::<()>();
+        adapter.spawn(Box::pin(async move {
+            let _ = tx.send(());
+        }));
+        let pending = adapter.spawn(Box::pin(futures::future::pending()));
+        rx.await.unwrap();
+        tokio::task::yield_now().await;
+        assert_eq!(adapter.running_tasks(), 1);
+
+        adapter.try_abort(pending);
+        assert_eq!(adapter.running_tasks(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tokio_clock() {
+        let adapter = MistyTokioAsyncTaskAdapter::new();
+        adapter.sleep(Duration::from_secs(5)).await;
+        let now = adapter.now();
+        assert!(now >= Duration::from_secs(5) && now < Duration::from_secs(6));
+    }
+
+    #[tokio::test]
+    async fn test_tokio_spawn_local() {
+        // outside any LocalSet of the caller
+        let adapter = MistyTokioAsyncTaskAdapter::new();
+        let (tx, rx) = oneshot::channel::<bool>();
+        adapter.spawn_local(Box::new(move || {
+            Box::pin(async move {
+                let done = std::rc::Rc::new(std::cell::Cell::new(false));
+                tokio::task::yield_now().await;
+                done.set(true);
+                let _ = tx.send(done.get());
+            })
+        }));
+        let pending = adapter.spawn_local(Box::new(|| Box::pin(futures::future::pending())));
+        assert!(rx.await.unwrap());
+        // the local task removes its handle after sending
+        for _ in 0..100 {
+            if adapter.running_tasks() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(adapter.running_tasks(), 1);
+
+        adapter.try_abort(pending);
+        assert_eq!(adapter.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_futures_adapter() {
+        let adapter = MistyFuturesAsyncTaskAdapter::new(ThreadPool::new().unwrap());
+        let mut local_pool = LocalPool::new();
+        MistyFuturesAsyncTaskAdapter::set_local_spawner(local_pool.spawner());
+
+        let (tx, rx) = oneshot::channel::<()>();
+        adapter.spawn(Box::pin(async move {
+            let _ = tx.send(());
+        }));
+        let done = Arc::new(AtomicBool::new(false));
+        {
+            let done = done.clone();
+            adapter.spawn_local(Box::new(move || {
+                Box::pin(async move {
+                    done.store(true, Ordering::SeqCst);
+                })
+            }));
+        }
+        let pending = adapter.spawn_local(Box::new(|| Box::pin(futures::future::pending())));
+
+        local_pool.run_until(rx).unwrap();
+        local_pool.run_until_stalled();
+        assert!(done.load(Ordering::SeqCst));
+        // the spawned task removes its handle after sending
+        for _ in 0..100 {
+            if adapter.running_tasks() == 1 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(adapter.running_tasks(), 1);
+
+        adapter.try_abort(pending);
+        local_pool.run_until_stalled();
+        assert_eq!(adapter.running_tasks(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_client_spawn_local() {
+        let client = MistyClient::new(
+            MistyViewModelManager::builder()
+                .register(counter_view_model)
+                .build(),
+            MistyStateManager::new(misty_states!(CounterState)),
+            MistyServiceManager::builder().build(),
+            MistyTokioAsyncTaskAdapter::new(),
+        );
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        client.on_signal(move |_| {
+            let _ = tx.unbounded_send(());
+        });
+
+        client.call_controller(controller_add_local, 2).unwrap();
+        rx.next().await.unwrap();
+        let ret = client.flush_scheduled_tasks().unwrap();
+        assert_eq!(ret.changed_view.unwrap().count, Some(2));
+    }
+}
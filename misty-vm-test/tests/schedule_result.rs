@@ -0,0 +1,172 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use futures::future::BoxFuture;
+use misty_vm::{
+    async_task::MistyAsyncTaskTrait, controllers::MistyControllerContext,
+    schedule::MistyScheduleError, states::MistyStateTrait, MistyAsyncTask, MistyState, MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct TodoState {
+    pub next_id: u32,
+    pub titles: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub titles: Vec<String>,
+}
+
+#[derive(Debug, MistyAsyncTask)]
+struct AddTodoAsyncTask;
+
+type Results = Arc<Mutex<Vec<Result<u32, String>>>>;
+
+fn controller_add_todo(
+    ctx: MistyControllerContext,
+    arg: (String, Results),
+) -> Result<(), Infallible> {
+    let (title, results) = arg;
+    AddTodoAsyncTask::spawn(&ctx, move |ctx| async move {
+        let res = ctx
+            .schedule_with_result(move |handle| {
+                TodoState::update(handle, |state| {
+                    state.next_id += 1;
+                    if title.is_empty() {
+                        return Err("empty title".to_string());
+                    }
+                    state.titles.push(title);
+                    Ok(state.next_id)
+                })
+            })
+            .await;
+        let res = res.map_err(|err| match err {
+            MistyScheduleError::Failed(err) => err,
+            MistyScheduleError::Cancelled => "cancelled".to_string(),
+        });
+        results.lock().unwrap().push(res);
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+type PendingResult =
+    Arc<Mutex<Option<BoxFuture<'static, Result<u32, MistyScheduleError<String>>>>>>;
+
+/// Leaves the result to be awaited outside the task.
+fn controller_add_todo_later(
+    ctx: MistyControllerContext,
+    arg: (String, PendingResult),
+) -> Result<(), Infallible> {
+    let (title, pending) = arg;
+    AddTodoAsyncTask::spawn(&ctx, move |ctx| async move {
+        let res = ctx.schedule_with_result(move |handle| {
+            TodoState::update(handle, |state| {
+                state.titles.push(title);
+            });
+            Ok::<u32, String>(0)
+        });
+        *pending.lock().unwrap() = Some(Box::pin(res));
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn todo_view_model(state: &TodoState, root: &mut RootViewModelState) {
+    root.titles = state.titles.clone();
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use misty_vm::{
+        client::MistyClient, errors::MistyErrorEvent, misty_states, schedule::MistyScheduleError,
+        services::MistyServiceManager, states::MistyStateManager, views::MistyViewModelManager,
+    };
+    use misty_vm_test::runtime::DeterministicRuntime;
+
+    use crate::{
+        controller_add_todo, controller_add_todo_later, todo_view_model, PendingResult, Results,
+        RootViewModelState, TodoState,
+    };
+
+    fn build_client(
+        runtime: &DeterministicRuntime,
+    ) -> (
+        MistyClient<RootViewModelState>,
+        Arc<Mutex<Vec<MistyErrorEvent>>>,
+    ) {
+        let client = MistyClient::new(
+            MistyViewModelManager::builder()
+                .register(todo_view_model)
+                .build(),
+            MistyStateManager::new(misty_states!(TodoState)),
+            MistyServiceManager::builder().build(),
+            runtime.adapter(),
+        );
+        client.on_signal(|_| {});
+        let events: Arc<Mutex<Vec<MistyErrorEvent>>> = Default::default();
+        {
+            let events = events.clone();
+            client.on_error(move |event| {
+                events.lock().unwrap().push(event.clone());
+            });
+        }
+        (client, events)
+    }
+
+    #[test]
+    fn test_schedule_with_result() {
+        let runtime = DeterministicRuntime::new();
+        let (client, events) = build_client(&runtime);
+        let results: Results = Default::default();
+
+        for title in ["a", "", "b"] {
+            client
+                .call_controller(controller_add_todo, (title.to_string(), results.clone()))
+                .unwrap();
+        }
+        runtime.run_until_idle();
+        assert!(results.lock().unwrap().is_empty());
+
+        let ret = client.flush_scheduled_tasks().unwrap();
+        runtime.run_until_idle();
+        assert_eq!(
+            results.lock().unwrap().clone(),
+            vec![Ok(1), Err("empty title".to_string()), Ok(2)]
+        );
+        // the failed handler is rolled back, and its error is not reported
+        assert_eq!(
+            ret.changed_view.unwrap().titles,
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert!(events.lock().unwrap().is_empty());
+        assert_eq!(runtime.running_tasks(), 0);
+    }
+
+    #[test]
+    fn test_cancel_on_destroy() {
+        let runtime = DeterministicRuntime::new();
+        let (client, _events) = build_client(&runtime);
+        let pending: PendingResult = Default::default();
+
+        client
+            .call_controller(
+                controller_add_todo_later,
+                ("a".to_string(), pending.clone()),
+            )
+            .unwrap();
+        runtime.run_until_idle();
+        client.destroy();
+
+        let res = pending.lock().unwrap().take().unwrap();
+        assert!(matches!(
+            futures::executor::block_on(res),
+            Err(MistyScheduleError::Cancelled)
+        ));
+    }
+}
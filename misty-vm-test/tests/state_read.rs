@@ -0,0 +1,182 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use misty_vm::{
+    async_task::{MistyAsyncTaskContext, MistyAsyncTaskTrait},
+    controllers::MistyControllerContext,
+    states::MistyStateTrait,
+    MistyAsyncTask, MistyState, MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct CartState {
+    pub items: Vec<u32>,
+}
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct TotalState {
+    pub total: u32,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub total: u32,
+}
+
+#[derive(Debug, MistyAsyncTask)]
+struct CartAsyncTask;
+
+type Contexts = Arc<Mutex<Option<MistyAsyncTaskContext>>>;
+
+fn controller_add_item(ctx: MistyControllerContext, arg: u32) -> Result<(), Infallible> {
+    CartState::update(&ctx, |state| state.items.push(arg));
+    TotalState::update(&ctx, |state| state.total += arg);
+    Ok(())
+}
+
+/// Leaves the context of a task to be used outside it.
+fn controller_keep_context(ctx: MistyControllerContext, arg: Contexts) -> Result<(), Infallible> {
+    CartAsyncTask::spawn(&ctx, move |ctx| async move {
+        *arg.lock().unwrap() = Some(ctx);
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+/// Schedules an update of the total from inside a read.
+fn controller_recount(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
+    CartAsyncTask::spawn(&ctx, move |ctx| async move {
+        ctx.read::<CartState, _>(|cart| {
+            let total = cart.items.iter().sum::<u32>() * 10;
+            ctx.schedule(move |handle| {
+                TotalState::update(handle, |state| state.total = total);
+                Ok::<(), Infallible>(())
+            });
+        });
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn cart_view_model(_state: &CartState, _root: &mut RootViewModelState) {}
+
+fn total_view_model(state: &TotalState, root: &mut RootViewModelState) {
+    root.total = state.total;
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use misty_vm::{
+        client::MistyClient, misty_states, services::MistyServiceManager,
+        states::MistyStateManager, views::MistyViewModelManager,
+    };
+    use misty_vm_test::{runtime::DeterministicRuntime, TestApp, TestAppContainer};
+
+    use crate::{
+        cart_view_model, controller_add_item, controller_keep_context, controller_recount,
+        total_view_model, CartState, Contexts, RootViewModelState, TotalState,
+    };
+
+    fn build_client(runtime: &DeterministicRuntime) -> MistyClient<RootViewModelState> {
+        let client = MistyClient::new(
+            MistyViewModelManager::builder()
+                .register(cart_view_model)
+                .register(total_view_model)
+                .build(),
+            MistyStateManager::new(misty_states!(CartState, TotalState)),
+            MistyServiceManager::builder().build(),
+            runtime.adapter(),
+        );
+        client.on_signal(|_| {});
+        client
+    }
+
+    fn keep_context(
+        runtime: &DeterministicRuntime,
+        client: &MistyClient<RootViewModelState>,
+    ) -> Contexts {
+        let contexts: Contexts = Default::default();
+        client
+            .call_controller(controller_keep_context, contexts.clone())
+            .unwrap();
+        runtime.run_until_idle();
+        contexts
+    }
+
+    #[test]
+    fn test_read() {
+        let runtime = DeterministicRuntime::new();
+        let client = build_client(&runtime);
+        client.call_controller(controller_add_item, 3).unwrap();
+        client.call_controller(controller_add_item, 4).unwrap();
+
+        let contexts = keep_context(&runtime, &client);
+        let ctx = contexts.lock().unwrap().take().unwrap();
+        assert_eq!(
+            ctx.read::<CartState, _>(|cart| cart.items.clone()),
+            Some(vec![3, 4])
+        );
+        assert_eq!(
+            ctx.read::<(CartState, TotalState), _>(|(cart, total)| {
+                (cart.items.len(), total.total)
+            }),
+            Some((2, 7))
+        );
+        assert!(ctx.try_handle().is_some());
+
+        client.destroy();
+        assert_eq!(ctx.read::<TotalState, _>(|total| total.total), None);
+        assert!(ctx.try_handle().is_none());
+    }
+
+    #[test]
+    fn test_consistent_read() {
+        let runtime = DeterministicRuntime::new();
+        let client = build_client(&runtime);
+        let contexts = keep_context(&runtime, &client);
+        let ctx = contexts.lock().unwrap().take().unwrap();
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                while !done.load(Ordering::SeqCst) {
+                    let (sum, total) = ctx
+                        .read::<(CartState, TotalState), _>(|(cart, total)| {
+                            (cart.items.iter().sum::<u32>(), total.total)
+                        })
+                        .unwrap();
+                    assert_eq!(sum, total);
+                }
+            });
+            for item in 0..500 {
+                client.call_controller(controller_add_item, item).unwrap();
+            }
+            done.store(true, Ordering::SeqCst);
+        });
+    }
+
+    #[test]
+    fn test_schedule_in_read() {
+        let runtime = DeterministicRuntime::new();
+        let app = TestApp::with_async_task_runtime(
+            MistyViewModelManager::builder()
+                .register(cart_view_model)
+                .register(total_view_model)
+                .build(),
+            MistyServiceManager::builder().build(),
+            MistyStateManager::new(misty_states!(CartState, TotalState)),
+            TestAppContainer::new(),
+            runtime.adapter(),
+        );
+        app.app().call_controller(controller_add_item, 3);
+
+        // the host flushes the schedule at once, while the task is still in its read
+        app.app().call_controller(controller_recount, ());
+        runtime.run_until_idle();
+        assert_eq!(app.state().total, 30);
+    }
+}
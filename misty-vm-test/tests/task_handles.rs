@@ -0,0 +1,183 @@
+use std::convert::Infallible;
+
+use misty_vm::{
+    async_task::{MistyAsyncTaskTrait, MistyTaskHandle},
+    controllers::MistyControllerContext,
+    states::MistyStateTrait,
+    MistyAsyncTask, MistyState, MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct JobState {
+    pub done: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub done: Vec<String>,
+}
+
+#[derive(Debug, MistyAsyncTask)]
+struct JobAsyncTask;
+
+#[derive(Debug, MistyAsyncTask)]
+struct WaitAsyncTask;
+
+fn controller_start_job(
+    ctx: MistyControllerContext,
+    _arg: (),
+) -> Result<MistyTaskHandle, Infallible> {
+    let handle = JobAsyncTask::spawn(&ctx, |ctx| async move {
+        ctx.cancelled().await;
+        Ok::<(), Infallible>(())
+    });
+    Ok(handle)
+}
+
+fn controller_start_short_job(
+    ctx: MistyControllerContext,
+    _arg: (),
+) -> Result<MistyTaskHandle, Infallible> {
+    Ok(JobAsyncTask::spawn(&ctx, |_ctx| async move {
+        Ok::<(), Infallible>(())
+    }))
+}
+
+fn controller_wait_job(
+    ctx: MistyControllerContext,
+    arg: (String, MistyTaskHandle),
+) -> Result<(), Infallible> {
+    let (name, job) = arg;
+    WaitAsyncTask::spawn(&ctx, move |ctx| async move {
+        job.finished().await;
+        ctx.schedule(move |handle| {
+            JobState::update(handle, |state| {
+                state.done.push(name);
+            });
+            Ok::<(), Infallible>(())
+        });
+        Ok::<(), Infallible>(())
+    });
+    Ok(())
+}
+
+fn controller_live_jobs(ctx: MistyControllerContext, _arg: ()) -> Result<Vec<u64>, Infallible> {
+    let ids = JobAsyncTask::live_tasks(&ctx)
+        .iter()
+        .map(|task| task.id())
+        .collect();
+    Ok(ids)
+}
+
+fn job_view_model(state: &JobState, root: &mut RootViewModelState) {
+    root.done = state.done.clone();
+}
+
+#[cfg(test)]
+mod test {
+    use misty_vm::{
+        misty_states, services::MistyServiceManager, states::MistyStateManager,
+        views::MistyViewModelManager,
+    };
+    use misty_vm_test::{runtime::DeterministicRuntime, TestApp, TestAppContainer};
+
+    use crate::{
+        controller_live_jobs, controller_start_job, controller_start_short_job,
+        controller_wait_job, job_view_model, JobState, RootViewModelState,
+    };
+
+    fn build_app(runtime: &DeterministicRuntime) -> TestApp<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(job_view_model)
+            .build();
+        let service_manager = MistyServiceManager::builder().build();
+        let state_manager = MistyStateManager::new(misty_states!(JobState));
+
+        TestApp::with_async_task_runtime(
+            view_manager,
+            service_manager,
+            state_manager,
+            TestAppContainer::new(),
+            runtime.adapter(),
+        )
+    }
+
+    #[test]
+    fn test_cancel_one_task() {
+        let runtime = DeterministicRuntime::new();
+        let app = build_app(&runtime);
+
+        let a = app.app().call_controller(controller_start_job, ());
+        let b = app.app().call_controller(controller_start_job, ());
+        app.app()
+            .call_controller(controller_wait_job, ("a".to_string(), a.clone()));
+        runtime.run_until_idle();
+        assert!(!a.is_finished());
+        assert_eq!(
+            app.app().call_controller(controller_live_jobs, ()),
+            vec![a.id(), b.id()]
+        );
+
+        a.cancel();
+        assert!(a.is_cancelled());
+        assert!(a.is_finished());
+        assert!(!b.is_cancelled());
+        assert_eq!(
+            app.app().call_controller(controller_live_jobs, ()),
+            vec![b.id()]
+        );
+
+        runtime.run_until_idle();
+        app.app().flush_schedules();
+        assert_eq!(app.state().done, vec!["a"]);
+
+        // cancelling again does nothing
+        a.cancel();
+        assert_eq!(runtime.running_tasks(), 1);
+    }
+
+    #[test]
+    fn test_finished_task() {
+        let runtime = DeterministicRuntime::new();
+        let app = build_app(&runtime);
+
+        let job = app.app().call_controller(controller_start_short_job, ());
+        assert!(!job.is_finished());
+        assert_eq!(app.app().live_async_tasks().len(), 1);
+
+        runtime.run_until_idle();
+        assert!(job.is_finished());
+        assert!(!job.is_cancelled());
+        assert!(app.app().live_async_tasks().is_empty());
+        futures::executor::block_on(job.finished());
+    }
+
+    #[test]
+    fn test_live_async_tasks() {
+        let runtime = DeterministicRuntime::new();
+        let app = build_app(&runtime);
+
+        let job = app.app().call_controller(controller_start_job, ());
+        app.app()
+            .call_controller(controller_wait_job, ("a".to_string(), job.clone()));
+        runtime.run_until_idle();
+
+        let live = app.app().live_async_tasks();
+        let live: Vec<(&str, usize)> = live
+            .iter()
+            .map(|live| (live.type_name, live.tasks.len()))
+            .collect();
+        assert_eq!(
+            live,
+            vec![
+                ("task_handles::JobAsyncTask", 1),
+                ("task_handles::WaitAsyncTask", 1)
+            ]
+        );
+
+        app.app().destroy();
+        assert!(job.is_cancelled());
+        assert!(job.is_finished());
+        assert!(app.app().live_async_tasks().is_empty());
+    }
+}
@@ -1,7 +1,7 @@
 use std::{convert::Infallible, time::Duration};
 
 use misty_vm::{
-    async_task::MistyAsyncTaskTrait, controllers::MistyControllerContext, misty_service, services::MistyServiceTrait, states::MistyStateTrait, MistyAsyncTask, MistyState
+    async_task::MistyAsyncTaskTrait, controllers::MistyControllerContext, misty_service, services::MistyServiceTrait, states::MistyStateTrait, MistyAsyncTask, MistyState, MistyView
 };
 
 #[derive(Debug, Default, Clone, MistyState)]
@@ -9,7 +9,7 @@ struct GlobalState {
     pub host_time: i64,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, MistyView)]
 struct RootViewModelState {
     pub host_time: i64,
 }
@@ -25,10 +25,10 @@ struct SpawnGetHostTimeIntervalAsyncTask;
 
 fn controller_initialize_app(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
     SpawnGetHostTimeIntervalAsyncTask::spawn_once(&ctx, |ctx| async move {
+        let mut interval = ctx.interval(Duration::from_secs(1));
         loop {
+            interval.tick().await;
             TimerService::of_async(&ctx).request_get_host_time();
-
-            tokio::time::sleep(Duration::from_secs(1)).await;
         }
         #[allow(unreachable_code)]
         Result::<(), Infallible>::Ok(())
@@ -58,7 +58,7 @@ mod test {
         misty_states, services::MistyServiceManager, states::MistyStateManager,
         views::MistyViewModelManager,
     };
-    use misty_vm_test::{TestApp, TestAppContainer};
+    use misty_vm_test::{runtime::DeterministicRuntime, TestApp, TestAppContainer};
 
     use crate::{
         controller_initialize_app, controller_set_host_time, timer_view_model, GlobalState,
@@ -90,10 +90,8 @@ mod test {
         }
     }
 
-    fn build_app() -> TestApp<RootViewModelState> {
-        let app_container = TestAppContainer::new(|changed, state| {
-            *state = changed;
-        });
+    fn build_app(runtime: &DeterministicRuntime) -> TestApp<RootViewModelState> {
+        let app_container = TestAppContainer::new();
         let fake_timer = FakeTimer::new();
 
         let view_manager = MistyViewModelManager::builder()
@@ -107,18 +105,29 @@ mod test {
             .build();
         let state_manager = MistyStateManager::new(misty_states!(GlobalState));
 
-        let app = TestApp::new(view_manager, service_manager, state_manager, app_container);
+        let app = TestApp::with_async_task_runtime(
+            view_manager,
+            service_manager,
+            state_manager,
+            app_container,
+            runtime.adapter(),
+        );
         app.app().call_controller(controller_initialize_app, ());
         app
     }
 
-    #[tokio::test]
-    async fn test_main() {
-        let app = build_app();
+    #[test]
+    fn test_main() {
+        let runtime = DeterministicRuntime::new();
+        let app = build_app(&runtime);
 
-        tokio::time::sleep(Duration::from_millis(2500)).await;
+        runtime.advance(Duration::from_millis(2500));
 
         let state = app.state();
         assert_eq!(state.host_time, 2);
+
+        runtime.advance(Duration::from_millis(500));
+        assert_eq!(app.state().host_time, 3);
+        assert_eq!(runtime.now(), Duration::from_secs(3));
     }
 }
@@ -38,7 +38,9 @@ mod global_states {
 }
 
 mod view_model_states {
-    #[derive(Debug, Clone)]
+    use misty_vm::MistyView;
+
+    #[derive(Debug, Clone, PartialEq)]
     pub struct TodolistItem {
         pub id: i32,
         pub checked: bool,
@@ -46,13 +48,15 @@ mod view_model_states {
         pub done: bool,
     }
 
-    #[derive(Debug, Clone, Default)]
+    #[derive(Debug, Clone, Default, PartialEq, MistyView)]
     pub struct Todolist {
+        #[misty(list(key = id))]
         pub list: Vec<TodolistItem>,
     }
 
-    #[derive(Debug, Clone, Default)]
+    #[derive(Debug, Clone, Default, MistyView)]
     pub struct Root {
+        #[misty(view)]
         pub todolist: Todolist,
     }
 }
@@ -64,7 +68,7 @@ pub struct ArgAddTodolistItem {
 fn controller_add_todolist_item(
     ctx: MistyControllerContext,
     arg: ArgAddTodolistItem,
-) -> Result<(), Infallible> {
+) -> Result<i32, Infallible> {
     let alloc_id = TodolistAllocState::update(&ctx, |alloc_state| {
         let id = alloc_state.alloc;
         alloc_state.alloc += 1;
@@ -81,7 +85,7 @@ fn controller_add_todolist_item(
             },
         );
     });
-    Ok(())
+    Ok(alloc_id)
 }
 
 fn controller_check_todolist_item(ctx: MistyControllerContext, id: i32) -> Result<(), Infallible> {
@@ -122,6 +126,7 @@ fn todolist_view_model(
             checked: checked.set.contains(&id),
         });
     }
+    vlist.list.sort_by_key(|item| item.id);
 
     root.todolist = vlist;
 }
@@ -141,9 +146,7 @@ mod test {
     };
 
     fn build_app() -> TestApp<view_model_states::Root> {
-        let app_container = TestAppContainer::new(|changed, state| {
-            *state = changed;
-        });
+        let app_container = TestAppContainer::new();
 
         let view_manager = MistyViewModelManager::builder()
             .register(todolist_view_model)
@@ -162,12 +165,13 @@ mod test {
     #[test]
     fn test_add() {
         let app = build_app();
-        app.app().call_controller(
+        let id = app.app().call_controller(
             controller_add_todolist_item,
             ArgAddTodolistItem {
                 title: "Math".to_string(),
             },
         );
+        assert_eq!(id, 1);
 
         let view = app.state();
         assert_eq!(view.todolist.list.len(), 1);
@@ -197,24 +201,25 @@ mod test {
     #[test]
     fn test_add_check_remove() {
         let app = build_app();
-        app.app().call_controller(
+        let math_id = app.app().call_controller(
             controller_add_todolist_item,
             ArgAddTodolistItem {
                 title: "Math".to_string(),
             },
         );
-        app.app().call_controller(
+        let english_id = app.app().call_controller(
             controller_add_todolist_item,
             ArgAddTodolistItem {
                 title: "English".to_string(),
             },
         );
-        app.app().call_controller(controller_check_todolist_item, 1);
+        app.app()
+            .call_controller(controller_check_todolist_item, math_id);
         app.app().call_controller(controller_remove_checked, ());
 
         let view = app.state();
         assert_eq!(view.todolist.list.len(), 1);
-        assert_eq!(view.todolist.list[0].id, 2);
+        assert_eq!(view.todolist.list[0].id, english_id);
         assert_eq!(view.todolist.list[0].title, "English");
         assert_eq!(view.todolist.list[0].checked, false);
     }
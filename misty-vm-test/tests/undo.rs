@@ -0,0 +1,176 @@
+use std::convert::Infallible;
+
+use misty_vm::{
+    controllers::MistyControllerContext, states::MistyStateTrait, undo::MistyUndoState, MistyState,
+    MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+#[misty(undoable)]
+struct TextState {
+    pub text: String,
+}
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct CursorState {
+    pub pos: usize,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+struct RootViewModelState {
+    pub text: String,
+    pub pos: usize,
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
+fn controller_type(ctx: MistyControllerContext, arg: &'static str) -> Result<(), Infallible> {
+    TextState::update(&ctx, |state| {
+        state.text.push_str(arg);
+    });
+    CursorState::update(&ctx, |state| {
+        state.pos += arg.len();
+    });
+    Ok(())
+}
+
+fn controller_move_cursor(ctx: MistyControllerContext, pos: usize) -> Result<(), Infallible> {
+    CursorState::update(&ctx, |state| {
+        state.pos = pos;
+    });
+    Ok(())
+}
+
+fn editor_view_model(
+    (text, cursor, undo): (&TextState, &CursorState, &MistyUndoState),
+    root: &mut RootViewModelState,
+) {
+    root.text = text.text.clone();
+    root.pos = cursor.pos;
+    root.can_undo = undo.can_undo;
+    root.can_redo = undo.can_redo;
+}
+
+#[cfg(test)]
+mod test {
+    use misty_vm::{
+        client::MistyClient,
+        controllers::MistyController,
+        misty_states,
+        states::MistyStateManager,
+        undo::{controller_redo, controller_undo},
+        views::{MistyViewModelManager, MistyViewTrait},
+    };
+    use misty_vm_test::fixtures::new_client;
+
+    use crate::{
+        controller_move_cursor, controller_type, editor_view_model, CursorState,
+        RootViewModelState, TextState,
+    };
+
+    struct TestEditor {
+        client: MistyClient<RootViewModelState>,
+        view: RootViewModelState,
+    }
+
+    impl TestEditor {
+        fn call<Arg>(
+            &mut self,
+            controller: impl MistyController<Arg, std::convert::Infallible>,
+            arg: Arg,
+        ) -> RootViewModelState {
+            let ret = self.client.call_controller(controller, arg).unwrap();
+            if let Some(patch) = ret.changed_view {
+                self.view.apply_patch(patch);
+            }
+            self.view.clone()
+        }
+    }
+
+    fn build_client(state_manager: MistyStateManager) -> TestEditor {
+        let view_manager = MistyViewModelManager::builder()
+            .register(editor_view_model)
+            .build();
+        let client = new_client(view_manager, state_manager);
+        TestEditor {
+            client,
+            view: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut client = build_client(MistyStateManager::new(misty_states!(
+            TextState,
+            CursorState
+        )));
+        client.call(controller_type, "a");
+        let view = client.call(controller_type, "b");
+        assert_eq!(view.text, "ab");
+        assert!(view.can_undo);
+        assert!(!view.can_redo);
+
+        let view = client.call(controller_undo, ());
+        assert_eq!(view.text, "a");
+        assert_eq!(view.pos, 2);
+        assert!(view.can_undo);
+        assert!(view.can_redo);
+
+        let view = client.call(controller_undo, ());
+        assert_eq!(view.text, "");
+        assert!(!view.can_undo);
+
+        let view = client.call(controller_undo, ());
+        assert_eq!(view.text, "");
+
+        let view = client.call(controller_redo, ());
+        assert_eq!(view.text, "a");
+        let view = client.call(controller_redo, ());
+        assert_eq!(view.text, "ab");
+        assert!(!view.can_redo);
+    }
+
+    #[test]
+    fn test_new_change_discards_redo() {
+        let mut client = build_client(MistyStateManager::new(misty_states!(
+            TextState,
+            CursorState
+        )));
+        client.call(controller_type, "a");
+        client.call(controller_type, "b");
+        client.call(controller_undo, ());
+
+        let view = client.call(controller_type, "c");
+        assert_eq!(view.text, "ac");
+        assert!(!view.can_redo);
+    }
+
+    #[test]
+    fn test_not_undoable_states_are_skipped() {
+        let mut client = build_client(MistyStateManager::new(misty_states!(
+            TextState,
+            CursorState
+        )));
+        client.call(controller_type, "a");
+        client.call(controller_move_cursor, 0);
+
+        let view = client.call(controller_undo, ());
+        assert_eq!(view.text, "");
+        assert_eq!(view.pos, 0);
+    }
+
+    #[test]
+    fn test_undo_limit() {
+        let mut client = build_client(
+            MistyStateManager::new(misty_states!(TextState, CursorState)).with_undo_limit(2),
+        );
+        client.call(controller_type, "a");
+        client.call(controller_type, "b");
+        client.call(controller_type, "c");
+
+        client.call(controller_undo, ());
+        let view = client.call(controller_undo, ());
+        assert_eq!(view.text, "a");
+        assert!(!view.can_undo);
+    }
+}
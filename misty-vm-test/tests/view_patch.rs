@@ -0,0 +1,131 @@
+use std::convert::Infallible;
+
+use misty_vm::{
+    controllers::MistyControllerContext, states::MistyStateTrait, MistyState, MistyView,
+};
+use misty_vm_test::fixtures::CounterState;
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct TitleState {
+    pub title: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, MistyView)]
+#[misty(patch_derive(Debug, PartialEq))]
+struct RootViewModelState {
+    pub count: i32,
+    pub title: String,
+}
+
+fn controller_add(ctx: MistyControllerContext, arg: i32) -> Result<(), Infallible> {
+    CounterState::update(&ctx, |state| {
+        state.count += arg;
+    });
+    Ok(())
+}
+
+fn controller_set_title(ctx: MistyControllerContext, arg: &'static str) -> Result<(), Infallible> {
+    TitleState::update(&ctx, |state| {
+        state.title = arg.to_string();
+    });
+    Ok(())
+}
+
+fn counter_view_model(state: &CounterState, root: &mut RootViewModelState) {
+    root.count = state.count;
+}
+
+fn title_view_model(state: &TitleState, root: &mut RootViewModelState) {
+    root.title = state.title.clone();
+}
+
+#[cfg(test)]
+mod test {
+    use misty_vm::{
+        client::MistyClient,
+        misty_states,
+        states::MistyStateManager,
+        views::{MistyViewModelManager, MistyViewTrait},
+    };
+    use misty_vm_test::fixtures::new_client;
+
+    use crate::{
+        controller_add, controller_set_title, counter_view_model, title_view_model, CounterState,
+        RootViewModelState, RootViewModelStatePatch, TitleState,
+    };
+
+    fn build_client() -> MistyClient<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(counter_view_model)
+            .register(title_view_model)
+            .build();
+        let state_manager = MistyStateManager::new(misty_states!(CounterState, TitleState));
+        new_client(view_manager, state_manager)
+    }
+
+    #[test]
+    fn test_patch_contains_changed_fields_only() {
+        let client = build_client();
+        client
+            .call_controller(controller_set_title, "misty")
+            .unwrap();
+
+        let ret = client.call_controller(controller_add, 1).unwrap();
+        assert_eq!(
+            ret.changed_view,
+            Some(RootViewModelStatePatch {
+                count: Some(1),
+                title: None,
+            })
+        );
+
+        let ret = client.call_controller(controller_add, 0).unwrap();
+        assert_eq!(ret.changed_view, None);
+
+        let ret = client.call_controller(controller_set_title, "vm").unwrap();
+        assert_eq!(
+            ret.changed_view,
+            Some(RootViewModelStatePatch {
+                count: None,
+                title: Some("vm".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_copy_changed_fields_only() {
+        let prev = RootViewModelState {
+            count: 1,
+            title: "misty".to_string(),
+        };
+        let next = RootViewModelState {
+            count: 2,
+            title: "vm".to_string(),
+        };
+        let mut view = prev.clone();
+        view.copy_changed(
+            &next,
+            &RootViewModelStatePatch {
+                count: Some(2),
+                title: None,
+            },
+        );
+        assert_eq!(view.count, 2);
+        assert_eq!(view.title, "misty");
+    }
+
+    #[test]
+    fn test_apply_patches_in_seq_order() {
+        let client = build_client();
+        let first = client.call_controller(controller_add, 1).unwrap();
+        let second = client.call_controller(controller_add, 2).unwrap();
+        assert_eq!(first.seq, Some(1));
+        assert_eq!(second.seq, Some(2));
+
+        let mut view = RootViewModelState::default();
+        view.apply_patch(first.changed_view.unwrap());
+        view.apply_patch(second.changed_view.unwrap());
+        assert_eq!(view.count, 3);
+        assert_eq!(view.title, "");
+    }
+}
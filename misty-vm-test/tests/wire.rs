@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+
+use misty_vm::{
+    controllers::MistyControllerContext, resources::MistyResourceHandle, states::MistyStateTrait,
+    MistyState, MistyView,
+};
+
+#[derive(Debug, Default, Clone, MistyState)]
+struct ImageState {
+    pub image: Option<MistyResourceHandle>,
+}
+
+#[derive(Debug, Default, Clone, MistyView)]
+#[misty(patch_derive(Debug, PartialEq, Serialize, Deserialize))]
+struct RootViewModelState {
+    pub image_id: u64,
+}
+
+fn controller_set_image(ctx: MistyControllerContext, buf: Vec<u8>) -> Result<u64, String> {
+    if buf.is_empty() {
+        return Err("empty image".to_string());
+    }
+    let handle = ctx.handle().resource_manager().insert(buf);
+    let id = *handle.id();
+    ImageState::update(&ctx, |state| {
+        state.image = Some(handle);
+    });
+    Ok(id)
+}
+
+fn controller_clear_image(ctx: MistyControllerContext, _arg: ()) -> Result<(), String> {
+    ImageState::update(&ctx, |state| {
+        state.image = None;
+    });
+    Ok(())
+}
+
+fn image_view_model(state: &ImageState, root: &mut RootViewModelState) {
+    root.image_id = state
+        .image
+        .as_ref()
+        .map(|image| *image.id())
+        .unwrap_or_default();
+}
+
+#[cfg(test)]
+mod test {
+    use misty_vm::{
+        client::MistyClient,
+        codecs::{MistyBincodeCodec, MistyCodec, MistyJsonCodec},
+        misty_states,
+        registry::MistyControllerRegistry,
+        resources::{MistyResourceId, ResourceUpdateAction},
+        states::MistyStateManager,
+        views::{MistyViewModelManager, MistyViewTrait},
+        wire::{
+            MistyWireDecodeError, MistyWireError, MistyWireErrorKind, MistyWireRet,
+            MISTY_WIRE_VERSION,
+        },
+    };
+    use misty_vm_test::fixtures::new_client;
+
+    use crate::{
+        controller_clear_image, controller_set_image, image_view_model, ImageState,
+        RootViewModelState,
+    };
+
+    type Patch = <RootViewModelState as MistyViewTrait>::Patch;
+
+    fn build_client(codec: impl MistyCodec) -> MistyClient<RootViewModelState> {
+        let view_manager = MistyViewModelManager::builder()
+            .register(image_view_model)
+            .build();
+        let state_manager = MistyStateManager::new(misty_states!(ImageState));
+        let client = new_client(view_manager, state_manager);
+        client.on_signal(|_| {});
+        client.set_controller_registry(
+            MistyControllerRegistry::builder(codec)
+                .register("set_image", controller_set_image)
+                .register("clear_image", controller_clear_image)
+                .build(),
+        );
+        client
+    }
+
+    fn check_roundtrip(codec: impl MistyCodec) {
+        let client = build_client(codec.clone());
+
+        let arg = codec.encode(&vec![1u8, 2, 3]).unwrap();
+        let buf = client.call_controller_by_name("set_image", &arg);
+        let ret = MistyWireRet::<Patch, u64>::decode(&codec, &buf).unwrap();
+        assert_eq!(ret.version, MISTY_WIRE_VERSION);
+        let value = ret.result.unwrap();
+        let id = MistyResourceId::wrap(value.value);
+        assert_eq!(
+            value.changed_view,
+            Some(Patch {
+                image_id: Some(value.value)
+            })
+        );
+        assert_eq!(
+            value.changed_resources,
+            vec![ResourceUpdateAction::Insert(id, vec![1, 2, 3])]
+        );
+
+        let arg = codec.encode(&()).unwrap();
+        let buf = client.call_controller_by_name("clear_image", &arg);
+        let ret = MistyWireRet::<Patch, ()>::decode(&codec, &buf).unwrap();
+        let value = ret.result.unwrap();
+        assert_eq!(value.changed_view, Some(Patch { image_id: Some(0) }));
+        assert_eq!(
+            value.changed_resources,
+            vec![ResourceUpdateAction::Remove(id)]
+        );
+    }
+
+    fn check_error(codec: impl MistyCodec) {
+        let client = build_client(codec.clone());
+
+        let arg = codec.encode(&Vec::<u8>::new()).unwrap();
+        let buf = client.call_controller_by_name("set_image", &arg);
+        let ret = MistyWireRet::<Patch, u64>::decode(&codec, &buf).unwrap();
+        assert_eq!(
+            ret.result,
+            Err(MistyWireError {
+                kind: MistyWireErrorKind::Failed,
+                message: "empty image".to_string(),
+            })
+        );
+
+        let buf = client.call_controller_by_name("set_images", &arg);
+        let ret = MistyWireRet::<Patch, u64>::decode(&codec, &buf).unwrap();
+        assert_eq!(
+            ret.result.unwrap_err().kind,
+            MistyWireErrorKind::UnknownController
+        );
+
+        let buf = client.call_controller_by_name("set_image", &[]);
+        let ret = MistyWireRet::<Patch, u64>::decode(&codec, &buf).unwrap();
+        assert_eq!(ret.result.unwrap_err().kind, MistyWireErrorKind::Codec);
+    }
+
+    #[test]
+    fn test_json_wire() {
+        check_roundtrip(MistyJsonCodec);
+        check_error(MistyJsonCodec);
+    }
+
+    #[test]
+    fn test_bincode_wire() {
+        check_roundtrip(MistyBincodeCodec);
+        check_error(MistyBincodeCodec);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut ret = MistyWireRet::<Patch, ()>::err(MistyWireError {
+            kind: MistyWireErrorKind::Rejected,
+            message: Default::default(),
+        });
+        ret.version = MISTY_WIRE_VERSION + 1;
+        let buf = MistyBincodeCodec.encode(&ret).unwrap();
+        let ret = MistyWireRet::<Patch, ()>::decode(&MistyBincodeCodec, &buf);
+        assert!(matches!(
+            ret,
+            Err(MistyWireDecodeError::UnsupportedVersion(version)) if version == MISTY_WIRE_VERSION + 1
+        ));
+    }
+}
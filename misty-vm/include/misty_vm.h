@@ -0,0 +1,86 @@
+/* Generated with cbindgen from the `ffi` feature of misty-vm. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * A client created by `misty_client_create`.
+ */
+typedef struct MistyFfiClient MistyFfiClient;
+
+/**
+ * Called from any thread when handlers are scheduled. The host should call
+ * `misty_client_flush_scheduled_tasks` on its own thread, not inside the callback.
+ */
+typedef void (*MistyScheduleCallback)(void *user_data);
+
+/**
+ * A buffer owned by Rust. It must be released by `misty_buffer_free`.
+ */
+typedef struct MistyFfiBuffer {
+  uint8_t *ptr;
+  uintptr_t len;
+  uintptr_t cap;
+} MistyFfiBuffer;
+
+/**
+ * Creates a client of a registered app. Returns null if `app_name` is not registered.
+ *
+ * # Safety
+ *
+ * `app_name` must be a valid nul-terminated string.
+ */
+MistyFfiClient *misty_client_create(const char *app_name,
+                                    MistyScheduleCallback on_schedule,
+                                    void *user_data);
+
+/**
+ * Calls the controller registered as `controller` with an encoded argument. An empty buffer
+ * is returned if the controller panics.
+ *
+ * # Safety
+ *
+ * `client` must be created by `misty_client_create` and not destroyed. `controller` must be a
+ * valid nul-terminated string, and `arg` must point to `arg_len` bytes.
+ */
+MistyFfiBuffer misty_client_call(const MistyFfiClient *client,
+                                 const char *controller,
+                                 const uint8_t *arg,
+                                 uintptr_t arg_len);
+
+/**
+ * Runs scheduled handlers, and returns the encoded view and resource updates.
+ *
+ * # Safety
+ *
+ * `client` must be created by `misty_client_create` and not destroyed.
+ */
+MistyFfiBuffer misty_client_flush_scheduled_tasks(const MistyFfiClient *client);
+
+/**
+ * Like `misty_client_flush_scheduled_tasks`, but returns an empty buffer without flushing if
+ * nothing is scheduled since the last flush.
+ *
+ * # Safety
+ *
+ * `client` must be created by `misty_client_create` and not destroyed.
+ */
+MistyFfiBuffer misty_client_poll(const MistyFfiClient *client);
+
+/**
+ * Destroys a client and releases it.
+ *
+ * # Safety
+ *
+ * `client` must be created by `misty_client_create`, and is invalid after the call.
+ */
+void misty_client_destroy(MistyFfiClient *client);
+
+/**
+ * # Safety
+ *
+ * `buf` must be returned by this library and not freed yet.
+ */
+void misty_buffer_free(MistyFfiBuffer buf);
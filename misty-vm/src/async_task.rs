@@ -1,39 +1,117 @@
 use std::{
     any::TypeId,
     collections::HashMap,
+    future::Future,
     marker::PhantomData,
-    sync::{atomic::AtomicU64, Arc, RwLock, Weak},
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, RwLock, Weak,
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
 };
 
-use futures::future::{BoxFuture, LocalBoxFuture};
+use futures::{
+    channel::oneshot,
+    future::{BoxFuture, LocalBoxFuture},
+    FutureExt,
+};
 
 use crate::{
     client::{
         AsMistyClientHandle, AsReadonlyMistyClientHandle, MistyClientAccessor, MistyClientHandle,
         MistyClientInner, MistyReadonlyClientHandle,
     },
+    errors::{panic_message, report_error, MistyErrorEvent, MistyErrorKind, MistyErrorSource},
+    retry::{run_with_retry, MistyRetryPolicy},
+    schedule::MistyScheduleError,
+    states::MistyReadStates,
+    timer::MistyInterval,
     utils::PhantomUnsync,
 };
 
+/// Creates a future that need not be `Send`, on the thread that runs it.
+pub type LocalFutureFn = Box<dyn FnOnce() -> LocalBoxFuture<'static, ()> + Send>;
+
 pub trait IAsyncTaskRuntimeAdapter {
     fn spawn(&self, future: BoxFuture<'static, ()>) -> u64;
-    fn spawn_local(&self, future: LocalBoxFuture<'static, ()>) -> u64;
+    /// The future is created by `future_fn`, so adapters can run it on a thread of their own.
+    fn spawn_local(&self, future_fn: LocalFutureFn) -> u64;
     fn try_abort(&self, task_id: u64);
+
+    /// Used by `ctx.sleep`, `ctx.interval`, retries and cancel grace periods.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    /// Monotonic time since a fixed point, on the clock `sleep` waits on. Used by `ctx.now`
+    /// and `ctx.interval`.
+    fn now(&self) -> Duration;
 }
 
+#[derive(Clone)]
 pub struct MistyAsyncTaskContext {
     pub(crate) inner: Weak<MistyClientInner>,
+    /// Set for async controllers, which resolve only after their scheduled handlers ran.
+    pub(crate) pending_schedules: Option<PendingSchedules>,
+    cancellation: MistyCancellationToken,
+    /// Type name of the async task, reported with errors of its scheduled handlers.
+    task_type: Option<&'static str>,
+}
+
+/// Signals a spawned task to stop. Tasks are cancelled by `spawn_once` and `cancel_all`, and
+/// by destroying the client.
+#[derive(Debug, Clone, Default)]
+pub struct MistyCancellationToken {
+    inner: Arc<Notify>,
+}
+
+/// A flag that is set once, waking the futures waiting for it.
+#[derive(Debug, Default)]
+struct Notify {
+    set: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// Completes when its token is cancelled.
+pub struct MistyCancelled {
+    token: MistyCancellationToken,
+}
+
+/// A spawned task, returned by the spawn methods of [`MistyAsyncTaskTrait`].
+#[derive(Debug, Clone)]
+pub struct MistyTaskHandle {
+    id: u64,
+    task: MistyAsyncTask,
+    pool: Weak<RwLock<InternalMistyAsyncTaskPool>>,
+    inner: Weak<MistyClientInner>,
 }
 
+/// Completes when its task is finished or aborted.
+pub struct MistyTaskFinished {
+    finished: Arc<Notify>,
+}
+
+/// Live tasks of a task type, listed by `MistyClient::live_async_tasks`.
+#[derive(Debug, Clone)]
+pub struct MistyLiveAsyncTasks {
+    pub type_name: &'static str,
+    pub tasks: Vec<MistyTaskHandle>,
+}
+
+pub(crate) type PendingSchedules = Arc<Mutex<Vec<oneshot::Receiver<()>>>>;
+
 pub struct MistyClientAsyncHandleGuard {
     inner: Option<Arc<MistyClientInner>>,
     _unsync_marker: PhantomUnsync,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone)]
 struct MistyAsyncTask {
-    id: u64,
     host_task_id: u64,
+    key: Option<String>,
+    token: MistyCancellationToken,
+    finished: Arc<Notify>,
 }
 
 fn alloc_task_id() -> u64 {
@@ -42,18 +120,22 @@ fn alloc_task_id() -> u64 {
     id
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct InternalMistyAsyncTaskPool {
+    type_name: &'static str,
+    cancel_grace_period: Option<Duration>,
     async_tasks: HashMap<u64, MistyAsyncTask>,
+    /// Tasks spawned by `spawn_once_keyed`, by their keys.
+    keyed_tasks: HashMap<String, u64>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 struct BoxedMistyAsyncTaskPool {
     pool: Arc<RwLock<InternalMistyAsyncTaskPool>>,
 }
 
 #[derive(Debug)]
-struct MistyAsyncTaskPool<T> {
+pub(crate) struct MistyAsyncTaskPool<T> {
     pool: Arc<RwLock<InternalMistyAsyncTaskPool>>,
     _marker: PhantomData<T>,
 }
@@ -65,29 +147,60 @@ pub(crate) struct MistyAsyncTaskPools {
     pools: Arc<RwLock<InternalMistyAsyncTaskPools>>,
 }
 
-struct MistyAsyncTaskPoolSpawnCleanupGuard<T>
-where
-    T: MistyAsyncTaskTrait,
-{
+/// Moved into the spawned future, so it is dropped when the task finishes or is aborted, even
+/// before being polled.
+struct MistyAsyncTaskPoolSpawnCleanupGuard {
     task_id: u64,
-    marker: PhantomData<T>,
-    pools: Weak<RwLock<InternalMistyAsyncTaskPools>>,
+    pool: Weak<RwLock<InternalMistyAsyncTaskPool>>,
+    finished: Arc<Notify>,
 }
 
-impl<T> Drop for MistyAsyncTaskPoolSpawnCleanupGuard<T>
-where
-    T: MistyAsyncTaskTrait,
-{
+impl Drop for MistyAsyncTaskPoolSpawnCleanupGuard {
     fn drop(&mut self) {
-        if let Some(pools) = self.pools.upgrade() {
-            let tid = std::any::TypeId::of::<T>();
-            let pool = pools.write().unwrap();
-            let pool = pool.get(&tid);
-            if let Some(pool) = pool {
-                let mut pool = pool.pool.write().unwrap();
-                pool.async_tasks.remove(&self.task_id);
+        let pool = self.pool.upgrade();
+        let mut pool = pool.as_ref().map(|pool| pool.write().unwrap());
+        if let Some(pool) = pool.as_mut() {
+            pool.remove(self.task_id);
+        }
+        // set while the pool is locked, so a task finished before being inserted is not inserted
+        self.finished.set();
+    }
+}
+
+/// Cancels the tokens of `tasks`, and aborts them at once or after `grace_period`.
+fn cancel_tasks(
+    inner: &Arc<MistyClientInner>,
+    grace_period: Option<Duration>,
+    tasks: Vec<MistyAsyncTask>,
+) {
+    if tasks.is_empty() {
+        return;
+    }
+    let rt = inner.async_task_runtime.as_ref();
+
+    for task in tasks.iter() {
+        task.token.cancel();
+    }
+    match grace_period {
+        None => {
+            for task in tasks.iter() {
+                rt.try_abort(task.host_task_id);
             }
         }
+        Some(grace_period) => {
+            let sleep = rt.sleep(grace_period);
+            let inner = Arc::downgrade(inner);
+            rt.spawn(Box::pin(async move {
+                sleep.await;
+                if let Some(inner) = inner.upgrade() {
+                    for task in tasks.iter() {
+                        if !task.finished.is_set() {
+                            inner.async_task_runtime.try_abort(task.host_task_id);
+                        }
+                    }
+                }
+            }));
+        }
     }
 }
 
@@ -98,12 +211,19 @@ impl MistyAsyncTaskPools {
         }
     }
 
-    fn get<T: 'static>(&self) -> MistyAsyncTaskPool<T> {
+    pub(crate) fn get<T: MistyAsyncTaskTrait>(&self) -> MistyAsyncTaskPool<T> {
         let pool = {
             let mut pools = self.pools.write().unwrap();
             let pool = pools
                 .entry(std::any::TypeId::of::<T>())
-                .or_default()
+                .or_insert_with(|| BoxedMistyAsyncTaskPool {
+                    pool: Arc::new(RwLock::new(InternalMistyAsyncTaskPool {
+                        type_name: std::any::type_name::<T>(),
+                        cancel_grace_period: T::cancel_grace_period(),
+                        async_tasks: Default::default(),
+                        keyed_tasks: Default::default(),
+                    })),
+                })
                 .clone();
             pool
         };
@@ -115,19 +235,100 @@ impl MistyAsyncTaskPools {
     }
 
     pub(crate) fn reset(&self, rt: &dyn IAsyncTaskRuntimeAdapter) {
-        let mut pools = self.pools.write().unwrap();
+        // aborted without holding the locks, since aborting may drop the cleanup guards at once
+        let pools = std::mem::take(&mut *self.pools.write().unwrap());
 
         for (_, pool) in pools.iter() {
-            let mut pool = pool.pool.write().unwrap();
-            for (_, task) in pool.async_tasks.iter() {
+            let tasks = pool.pool.write().unwrap().take_all();
+            for task in tasks.iter() {
+                task.token.cancel();
                 rt.try_abort(task.host_task_id);
             }
-            pool.async_tasks.clear();
         }
-        pools.clear();
+    }
+
+    pub(crate) fn live_tasks(&self, inner: &Arc<MistyClientInner>) -> Vec<MistyLiveAsyncTasks> {
+        let pools = self.pools.read().unwrap();
+        let mut live_tasks: Vec<MistyLiveAsyncTasks> = pools
+            .values()
+            .map(|pool| MistyLiveAsyncTasks {
+                type_name: pool.pool.read().unwrap().type_name,
+                tasks: task_handles(&pool.pool, inner),
+            })
+            .filter(|live| !live.tasks.is_empty())
+            .collect();
+        live_tasks.sort_by_key(|live| live.type_name);
+        live_tasks
+    }
+}
+
+fn report_task_result<T, E>(inner: &MistyClientInner, res: std::thread::Result<Result<(), E>>)
+where
+    E: std::fmt::Display,
+{
+    let kind = match res {
+        Ok(Ok(())) => return,
+        Ok(Err(e)) => {
+            tracing::error!("spawn error: {}", e);
+            MistyErrorKind::Error(e.to_string())
+        }
+        Err(payload) => {
+            tracing::error!("spawn panic");
+            MistyErrorKind::Panic(panic_message(payload.as_ref()))
+        }
+    };
+    let event = MistyErrorEvent {
+        source: MistyErrorSource::Spawn,
+        task_type: Some(std::any::type_name::<T>()),
+        kind,
+    };
+    report_error(inner, event, true);
+}
+
+impl InternalMistyAsyncTaskPool {
+    fn remove(&mut self, task_id: u64) -> Option<MistyAsyncTask> {
+        let task = self.async_tasks.remove(&task_id)?;
+        if let Some(key) = task.key.as_ref() {
+            if self.keyed_tasks.get(key) == Some(&task_id) {
+                self.keyed_tasks.remove(key);
+            }
+        }
+        Some(task)
+    }
+
+    fn remove_keyed(&mut self, key: &str) -> Option<MistyAsyncTask> {
+        let task_id = self.keyed_tasks.remove(key)?;
+        self.async_tasks.remove(&task_id)
+    }
+
+    fn take_all(&mut self) -> Vec<MistyAsyncTask> {
+        self.keyed_tasks.clear();
+        std::mem::take(&mut self.async_tasks)
+            .into_values()
+            .collect()
     }
 }
 
+fn task_handles(
+    pool: &Arc<RwLock<InternalMistyAsyncTaskPool>>,
+    inner: &Arc<MistyClientInner>,
+) -> Vec<MistyTaskHandle> {
+    let mut handles: Vec<MistyTaskHandle> = pool
+        .read()
+        .unwrap()
+        .async_tasks
+        .iter()
+        .map(|(id, task)| MistyTaskHandle {
+            id: *id,
+            task: task.clone(),
+            pool: Arc::downgrade(pool),
+            inner: Arc::downgrade(inner),
+        })
+        .collect();
+    handles.sort_by_key(|handle| handle.id);
+    handles
+}
+
 impl<T> MistyAsyncTaskPool<T>
 where
     T: MistyAsyncTaskTrait,
@@ -135,86 +336,172 @@ where
     pub fn spawn<R, E>(
         &self,
         handle: MistyReadonlyClientHandle,
+        key: Option<String>,
         future_fn: impl (FnOnce(MistyAsyncTaskContext) -> R) + Send + 'static,
-    ) where
+    ) -> MistyTaskHandle
+    where
         R: std::future::Future<Output = Result<(), E>> + Send + 'static,
         E: std::fmt::Display,
     {
         let inner = handle.inner.clone();
         let cloned_inner = inner.clone();
         let task_id = alloc_task_id();
+        let token = MistyCancellationToken::default();
+        let cloned_token = token.clone();
+        let finished: Arc<Notify> = Default::default();
+        let guard = MistyAsyncTaskPoolSpawnCleanupGuard {
+            task_id,
+            pool: Arc::downgrade(&self.pool),
+            finished: finished.clone(),
+        };
 
         let host_task_id = inner.async_task_runtime.spawn(Box::pin(async move {
+            let _guard = guard;
             let inner = cloned_inner;
-            let _guard = MistyAsyncTaskPoolSpawnCleanupGuard::<T> {
-                task_id,
-                marker: Default::default(),
-                pools: Arc::downgrade(&inner.async_task_pools.pools),
-            };
 
-            let ctx = MistyAsyncTaskContext::new(Arc::downgrade(&inner));
-            let res = future_fn(ctx).await;
-            if res.is_err() {
-                let e = res.unwrap_err();
-                tracing::error!("spawn error: {}", e);
-            }
+            let ctx = MistyAsyncTaskContext::new(
+                Arc::downgrade(&inner),
+                cloned_token,
+                std::any::type_name::<T>(),
+            );
+            let res = AssertUnwindSafe(async move { future_fn(ctx).await })
+                .catch_unwind()
+                .await;
+            report_task_result::<T, E>(&inner, res);
         }));
 
-        let task = MistyAsyncTask {
-            id: task_id,
-            host_task_id,
-        };
-        {
-            let mut pool = self.pool.write().unwrap();
-            pool.async_tasks.insert(task_id, task);
-        }
+        self.insert(&inner, task_id, host_task_id, key, token, finished)
     }
 
     pub fn spawn_local<R, E>(
         &self,
         handle: MistyReadonlyClientHandle,
-        future_fn: impl (FnOnce(MistyAsyncTaskContext) -> R) + 'static,
-    ) where
+        key: Option<String>,
+        future_fn: impl (FnOnce(MistyAsyncTaskContext) -> R) + Send + 'static,
+    ) -> MistyTaskHandle
+    where
         R: std::future::Future<Output = Result<(), E>> + 'static,
         E: std::fmt::Display,
     {
         let inner = handle.inner.clone();
         let cloned_inner = inner.clone();
         let task_id = alloc_task_id();
+        let token = MistyCancellationToken::default();
+        let cloned_token = token.clone();
+        let finished: Arc<Notify> = Default::default();
+        let guard = MistyAsyncTaskPoolSpawnCleanupGuard {
+            task_id,
+            pool: Arc::downgrade(&self.pool),
+            finished: finished.clone(),
+        };
 
-        let host_task_id = inner.async_task_runtime.spawn_local(Box::pin(async move {
-            let inner = cloned_inner;
-            let _guard = MistyAsyncTaskPoolSpawnCleanupGuard::<T> {
-                task_id,
-                marker: Default::default(),
-                pools: Arc::downgrade(&inner.async_task_pools.pools),
-            };
-
-            let ctx = MistyAsyncTaskContext::new(Arc::downgrade(&inner));
-            let res = future_fn(ctx).await;
-            if res.is_err() {
-                let e = res.unwrap_err();
-                tracing::error!("spawn error: {}", e);
-            }
+        let host_task_id = inner.async_task_runtime.spawn_local(Box::new(move || {
+            Box::pin(async move {
+                let _guard = guard;
+                let inner = cloned_inner;
+
+                let ctx = MistyAsyncTaskContext::new(
+                    Arc::downgrade(&inner),
+                    cloned_token,
+                    std::any::type_name::<T>(),
+                );
+                let res = AssertUnwindSafe(async move { future_fn(ctx).await })
+                    .catch_unwind()
+                    .await;
+                report_task_result::<T, E>(&inner, res);
+            })
         }));
 
+        self.insert(&inner, task_id, host_task_id, key, token, finished)
+    }
+
+    fn insert(
+        &self,
+        inner: &Arc<MistyClientInner>,
+        task_id: u64,
+        host_task_id: u64,
+        key: Option<String>,
+        token: MistyCancellationToken,
+        finished: Arc<Notify>,
+    ) -> MistyTaskHandle {
         let task = MistyAsyncTask {
-            id: task_id,
             host_task_id,
+            key,
+            token,
+            finished,
         };
         {
             let mut pool = self.pool.write().unwrap();
-            pool.async_tasks.insert(task_id, task);
+            if !task.finished.is_set() {
+                if let Some(key) = task.key.clone() {
+                    pool.keyed_tasks.insert(key, task_id);
+                }
+                pool.async_tasks.insert(task_id, task.clone());
+            }
+        }
+        MistyTaskHandle {
+            id: task_id,
+            task,
+            pool: Arc::downgrade(&self.pool),
+            inner: Arc::downgrade(inner),
+        }
+    }
+
+    /// Cancels the tokens of all tasks. They are aborted at once, or after the grace period of
+    /// the task type, so they can finish their work.
+    pub fn cancel_all(&self, inner: &Arc<MistyClientInner>) {
+        let tasks = self.pool.write().unwrap().take_all();
+        cancel_tasks(inner, T::cancel_grace_period(), tasks);
+    }
+
+    /// Cancels the task spawned with `key`, like `cancel_all` does.
+    pub fn cancel_keyed(&self, inner: &Arc<MistyClientInner>, key: &str) {
+        let task = self.pool.write().unwrap().remove_keyed(key);
+        cancel_tasks(inner, T::cancel_grace_period(), task.into_iter().collect());
+    }
+
+    pub fn live_tasks(&self, inner: &Arc<MistyClientInner>) -> Vec<MistyTaskHandle> {
+        task_handles(&self.pool, inner)
+    }
+}
+
+impl MistyTaskHandle {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Cancels the task like `cancel_all` does. Finished or cancelled tasks are left as is.
+    pub fn cancel(&self) {
+        let (Some(pool), Some(inner)) = (self.pool.upgrade(), self.inner.upgrade()) else {
+            return;
+        };
+        let (task, grace_period) = {
+            let mut pool = pool.write().unwrap();
+            (pool.remove(self.id), pool.cancel_grace_period)
+        };
+        if let Some(task) = task {
+            cancel_tasks(&inner, grace_period, vec![task]);
         }
     }
 
-    pub fn cancel_all(&self, rt: &dyn IAsyncTaskRuntimeAdapter) {
-        let mut pool = self.pool.write().unwrap();
+    /// The key of a task spawned by `spawn_once_keyed`.
+    pub fn key(&self) -> Option<&str> {
+        self.task.key.as_deref()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.task.token.is_cancelled()
+    }
 
-        for (_, task) in pool.async_tasks.iter() {
-            rt.try_abort(task.host_task_id);
+    /// Whether the task is finished or aborted.
+    pub fn is_finished(&self) -> bool {
+        self.task.finished.is_set()
+    }
+
+    pub fn finished(&self) -> MistyTaskFinished {
+        MistyTaskFinished {
+            finished: self.task.finished.clone(),
         }
-        pool.async_tasks.clear();
     }
 }
 
@@ -226,11 +513,137 @@ impl MistyClientAsyncHandleGuard {
     }
 }
 
+impl Notify {
+    fn set(&self) {
+        if self.set.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let wakers = std::mem::take(&mut *self.wakers.lock().unwrap());
+        for waker in wakers.into_iter() {
+            waker.wake();
+        }
+    }
+
+    fn is_set(&self) -> bool {
+        self.set.load(Ordering::SeqCst)
+    }
+
+    fn poll_set(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_set() {
+            return Poll::Ready(());
+        }
+        {
+            let mut wakers = self.wakers.lock().unwrap();
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+        }
+        // set before the waker is registered
+        if self.is_set() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+impl MistyCancellationToken {
+    pub fn cancel(&self) {
+        self.inner.set();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_set()
+    }
+
+    pub fn cancelled(&self) -> MistyCancelled {
+        MistyCancelled {
+            token: self.clone(),
+        }
+    }
+}
+
+impl Future for MistyCancelled {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.token.inner.poll_set(cx)
+    }
+}
+
+impl Future for MistyTaskFinished {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.finished.poll_set(cx)
+    }
+}
+
 impl MistyAsyncTaskContext {
-    fn new(inner: Weak<MistyClientInner>) -> Self {
-        Self { inner }
+    fn new(
+        inner: Weak<MistyClientInner>,
+        cancellation: MistyCancellationToken,
+        task_type: &'static str,
+    ) -> Self {
+        Self {
+            inner,
+            pending_schedules: None,
+            cancellation,
+            task_type: Some(task_type),
+        }
+    }
+
+    pub(crate) fn tracked(
+        inner: Weak<MistyClientInner>,
+        pending_schedules: PendingSchedules,
+    ) -> Self {
+        Self {
+            inner,
+            pending_schedules: Some(pending_schedules),
+            cancellation: Default::default(),
+            task_type: None,
+        }
+    }
+
+    /// Whether the task is cancelled. Async controllers are never cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
     }
 
+    /// Completes when the task is cancelled, so it can stop at a point of its choosing.
+    pub fn cancelled(&self) -> MistyCancelled {
+        self.cancellation.cancelled()
+    }
+
+    pub fn cancellation_token(&self) -> MistyCancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Sleeps on the async task runtime. `None` if the client is released.
+    pub(crate) fn runtime_sleep(&self, duration: Duration) -> Option<BoxFuture<'static, ()>> {
+        let inner = self.inner.upgrade()?;
+        Some(inner.async_task_runtime.sleep(duration))
+    }
+
+    /// Time of the async task runtime. Zero if the client is released.
+    pub fn now(&self) -> Duration {
+        match self.inner.upgrade() {
+            Some(inner) => inner.async_task_runtime.now(),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Sleeps on the async task runtime. Never completes if the client is released.
+    pub fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self.runtime_sleep(duration)
+            .unwrap_or_else(|| Box::pin(futures::future::pending()))
+    }
+
+    /// Panics if `period` is zero.
+    pub fn interval(&self, period: Duration) -> MistyInterval {
+        MistyInterval::new(self.inner.clone(), period)
+    }
+
+    /// The handle of the guard panics if the client is released. See `try_handle`.
     pub fn handle(&self) -> MistyClientAsyncHandleGuard {
         let inner = self.inner.upgrade();
         MistyClientAsyncHandleGuard {
@@ -239,6 +652,26 @@ impl MistyAsyncTaskContext {
         }
     }
 
+    /// Like `handle`, but `None` if the client is released or destroyed.
+    pub fn try_handle(&self) -> Option<MistyClientAsyncHandleGuard> {
+        let inner = self.inner.upgrade().filter(|inner| !inner.is_destroyed())?;
+        Some(MistyClientAsyncHandleGuard {
+            inner: Some(inner),
+            _unsync_marker: Default::default(),
+        })
+    }
+
+    /// Reads a state, or a tuple of states consistent with each other, like
+    /// `ctx.read::<(AState, BState), _>(|(a, b)| ..)`. `None` if the client is released or
+    /// destroyed.
+    pub fn read<S, R>(&self, func: impl FnOnce(S::Refs<'_>) -> R) -> Option<R>
+    where
+        S: MistyReadStates,
+    {
+        let inner = self.inner.upgrade().filter(|inner| !inner.is_destroyed())?;
+        Some(S::read(&inner.state_manager, func))
+    }
+
     pub fn accessor(&self) -> MistyClientAccessor {
         MistyClientAccessor {
             inner: self.inner.clone(),
@@ -261,64 +694,269 @@ impl MistyAsyncTaskContext {
             tracing::warn!("schedule but client is destroyed");
             return;
         }
+        if let Some(pending_schedules) = self.pending_schedules.as_ref() {
+            // the sender is dropped without sending if the handler never runs or panics
+            let (tx, rx) = oneshot::channel::<()>();
+            pending_schedules.lock().unwrap().push(rx);
+            inner
+                .schedule_manager
+                .enqueue(&inner.signal_emitter, self.task_type, move |handle| {
+                    let res = handler(handle);
+                    let _ = tx.send(());
+                    res
+                });
+        } else {
+            inner
+                .schedule_manager
+                .enqueue(&inner.signal_emitter, self.task_type, handler);
+        }
+    }
+
+    /// Like `schedule`, but resolves to the result of the handler once the host flushes the
+    /// scheduled tasks. Errors of the handler are returned here instead of reported.
+    pub fn schedule_with_result<T, E>(
+        &self,
+        handler: impl FnOnce(MistyClientHandle) -> Result<T, E> + Send + Sync + 'static,
+    ) -> impl Future<Output = Result<T, MistyScheduleError<E>>> + Send + 'static
+    where
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        let rx = self.inner.upgrade().and_then(|inner| {
+            if inner.is_destroyed() {
+                tracing::warn!("schedule but client is destroyed");
+                return None;
+            }
+            let rx = if let Some(pending_schedules) = self.pending_schedules.as_ref() {
+                let (tx, rx) = oneshot::channel::<()>();
+                pending_schedules.lock().unwrap().push(rx);
+                inner.schedule_manager.enqueue_with_result(
+                    &inner.signal_emitter,
+                    self.task_type,
+                    move |handle| {
+                        let res = handler(handle);
+                        let _ = tx.send(());
+                        res
+                    },
+                )
+            } else {
+                inner.schedule_manager.enqueue_with_result(
+                    &inner.signal_emitter,
+                    self.task_type,
+                    handler,
+                )
+            };
+            Some(rx)
+        });
+
+        async move {
+            let Some(rx) = rx else {
+                return Err(MistyScheduleError::Cancelled);
+            };
+            match rx.await {
+                Ok(res) => res.map_err(MistyScheduleError::Failed),
+                Err(_) => Err(MistyScheduleError::Cancelled),
+            }
+        }
+    }
+
+    /// Like `schedule`, but after `duration`. Async controllers do not wait for delayed
+    /// handlers, which are cancelled if the client is destroyed before then.
+    pub fn schedule_after<E>(
+        &self,
+        duration: Duration,
+        handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
+    ) where
+        E: std::fmt::Display,
+    {
+        let Some(inner) = self.inner.upgrade() else {
+            return;
+        };
         inner
             .schedule_manager
-            .enqueue(&inner.signal_emitter, handler);
+            .enqueue_after(&inner, self.task_type, duration, handler);
+    }
+
+    /// Schedules the handler once no handler with the same tag is debounced for `duration`.
+    /// Only the last handler runs.
+    pub fn schedule_debounced<E>(
+        &self,
+        tag: impl ToString,
+        duration: Duration,
+        handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
+    ) where
+        E: std::fmt::Display,
+    {
+        let Some(inner) = self.inner.upgrade() else {
+            return;
+        };
+        inner.schedule_manager.enqueue_debounced(
+            &inner,
+            self.task_type,
+            tag.to_string(),
+            duration,
+            handler,
+        );
+    }
+
+    /// Schedules at most one handler with the same tag every `duration`. The first handler runs
+    /// at once, and the last one throttled during the window runs when it ends.
+    pub fn schedule_throttled<E>(
+        &self,
+        tag: impl ToString,
+        duration: Duration,
+        handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
+    ) where
+        E: std::fmt::Display,
+    {
+        let Some(inner) = self.inner.upgrade() else {
+            return;
+        };
+        inner.schedule_manager.enqueue_throttled(
+            &inner,
+            self.task_type,
+            tag.to_string(),
+            duration,
+            handler,
+        );
     }
 }
 
 pub trait MistyAsyncTaskTrait: Sized + Send + Sync + 'static {
+    /// How long cancelled tasks may keep running before they are aborted. `None` aborts them
+    /// right after cancelling their tokens.
+    fn cancel_grace_period() -> Option<Duration> {
+        None
+    }
+
     fn spawn_once<'a, T, E>(
         cx: impl AsMistyClientHandle<'a>,
         future_fn: impl (FnOnce(MistyAsyncTaskContext) -> T) + Send + Sync + 'static,
-    ) where
+    ) -> MistyTaskHandle
+    where
+        T: std::future::Future<Output = Result<(), E>> + Send + 'static,
+        E: std::fmt::Display,
+    {
+        let inner = cx.handle().inner;
+        let pool = inner.async_task_pools.get::<Self>();
+        pool.cancel_all(inner);
+        pool.spawn(cx.readonly_handle().clone(), None, future_fn)
+    }
+
+    /// Like `spawn_once`, but only cancels the task spawned with the same `key`, so tasks with
+    /// different keys run concurrently.
+    fn spawn_once_keyed<'a, T, E>(
+        cx: impl AsMistyClientHandle<'a>,
+        key: impl ToString,
+        future_fn: impl (FnOnce(MistyAsyncTaskContext) -> T) + Send + Sync + 'static,
+    ) -> MistyTaskHandle
+    where
         T: std::future::Future<Output = Result<(), E>> + Send + 'static,
         E: std::fmt::Display,
     {
         let inner = cx.handle().inner;
+        let key = key.to_string();
         let pool = inner.async_task_pools.get::<Self>();
-        pool.cancel_all(inner.async_task_runtime.as_ref());
-        pool.spawn(cx.readonly_handle().clone(), future_fn);
+        pool.cancel_keyed(inner, &key);
+        pool.spawn(cx.readonly_handle(), Some(key), future_fn)
     }
 
     fn spawn<'a, T, E>(
         cx: impl AsMistyClientHandle<'a>,
         future_fn: impl (FnOnce(MistyAsyncTaskContext) -> T) + Send + Sync + 'static,
-    ) where
+    ) -> MistyTaskHandle
+    where
         T: std::future::Future<Output = Result<(), E>> + Send + 'static,
         E: std::fmt::Display,
     {
         let pool = cx.handle().inner.async_task_pools.get::<Self>();
-        pool.spawn(cx.readonly_handle(), future_fn);
+        pool.spawn(cx.readonly_handle(), None, future_fn)
+    }
+
+    /// Like `spawn`, but runs `future_fn` again after it fails, as `policy` allows. Retries wait
+    /// on the async task runtime, and a cancelled task stops retrying.
+    fn spawn_with_retry<'a, T, E>(
+        cx: impl AsMistyClientHandle<'a>,
+        policy: MistyRetryPolicy<E>,
+        future_fn: impl (Fn(MistyAsyncTaskContext) -> T) + Send + Sync + 'static,
+    ) -> MistyTaskHandle
+    where
+        T: std::future::Future<Output = Result<(), E>> + Send + 'static,
+        E: std::fmt::Display + Send + 'static,
+    {
+        Self::spawn(cx, move |ctx| run_with_retry(ctx, policy, future_fn))
+    }
+
+    fn spawn_once_with_retry<'a, T, E>(
+        cx: impl AsMistyClientHandle<'a>,
+        policy: MistyRetryPolicy<E>,
+        future_fn: impl (Fn(MistyAsyncTaskContext) -> T) + Send + Sync + 'static,
+    ) -> MistyTaskHandle
+    where
+        T: std::future::Future<Output = Result<(), E>> + Send + 'static,
+        E: std::fmt::Display + Send + 'static,
+    {
+        Self::spawn_once(cx, move |ctx| run_with_retry(ctx, policy, future_fn))
     }
 
     fn spawn_local_once<'a, T, E>(
         cx: impl AsMistyClientHandle<'a>,
-        future_fn: impl (FnOnce(MistyAsyncTaskContext) -> T) + 'static,
-    ) where
+        future_fn: impl (FnOnce(MistyAsyncTaskContext) -> T) + Send + 'static,
+    ) -> MistyTaskHandle
+    where
+        T: std::future::Future<Output = Result<(), E>> + 'static,
+        E: std::fmt::Display,
+    {
+        let inner = cx.handle().inner;
+        let pool = inner.async_task_pools.get::<Self>();
+        pool.cancel_all(inner);
+        pool.spawn_local(cx.readonly_handle().clone(), None, future_fn)
+    }
+
+    fn spawn_local_once_keyed<'a, T, E>(
+        cx: impl AsMistyClientHandle<'a>,
+        key: impl ToString,
+        future_fn: impl (FnOnce(MistyAsyncTaskContext) -> T) + Send + 'static,
+    ) -> MistyTaskHandle
+    where
         T: std::future::Future<Output = Result<(), E>> + 'static,
         E: std::fmt::Display,
     {
         let inner = cx.handle().inner;
+        let key = key.to_string();
         let pool = inner.async_task_pools.get::<Self>();
-        pool.cancel_all(inner.async_task_runtime.as_ref());
-        pool.spawn_local(cx.readonly_handle().clone(), future_fn);
+        pool.cancel_keyed(inner, &key);
+        pool.spawn_local(cx.readonly_handle(), Some(key), future_fn)
     }
 
     fn spawn_local<'a, T, E>(
         cx: impl AsMistyClientHandle<'a>,
-        future_fn: impl (FnOnce(MistyAsyncTaskContext) -> T) + 'static,
-    ) where
+        future_fn: impl (FnOnce(MistyAsyncTaskContext) -> T) + Send + 'static,
+    ) -> MistyTaskHandle
+    where
         T: std::future::Future<Output = Result<(), E>> + 'static,
         E: std::fmt::Display,
     {
         let pool = cx.handle().inner.async_task_pools.get::<Self>();
-        pool.spawn_local(cx.readonly_handle(), future_fn);
+        pool.spawn_local(cx.readonly_handle(), None, future_fn)
     }
 
     fn cancel_all<'a>(cx: impl AsMistyClientHandle<'a>) {
         let inner = cx.handle().inner;
         let pool = inner.async_task_pools.get::<Self>();
-        pool.cancel_all(inner.async_task_runtime.as_ref());
+        pool.cancel_all(inner);
+    }
+
+    /// Cancels the task spawned by `spawn_once_keyed` with `key`.
+    fn cancel_keyed<'a>(cx: impl AsMistyClientHandle<'a>, key: impl ToString) {
+        let inner = cx.handle().inner;
+        let pool = inner.async_task_pools.get::<Self>();
+        pool.cancel_keyed(inner, &key.to_string());
+    }
+
+    /// Tasks of this type that are neither finished nor cancelled.
+    fn live_tasks<'a>(cx: impl AsMistyClientHandle<'a>) -> Vec<MistyTaskHandle> {
+        let inner = cx.handle().inner;
+        inner.async_task_pools.get::<Self>().live_tasks(inner)
     }
 }
@@ -5,10 +5,12 @@ use std::{
 
 use crate::{
     async_task::{IAsyncTaskRuntimeAdapter, MistyAsyncTaskPools},
+    errors::MistyErrorSink,
+    middlewares::MistyMiddlewareManager,
     resources::MistyResourceManager,
     schedule::ScheduleManager,
     services::MistyServiceManager,
-    signals::SignalEmitter,
+    signals::{MistySignal, SignalEmitter},
     states::MistyStateManager,
     views::ViewNotifier,
 };
@@ -43,6 +45,8 @@ pub(crate) struct MistyClientInner {
     pub async_task_runtime: Box<dyn IAsyncTaskRuntimeAdapter + Send + Sync>,
     pub schedule_manager: ScheduleManager,
     pub signal_emitter: SignalEmitter,
+    pub middleware_manager: MistyMiddlewareManager,
+    pub error_sink: MistyErrorSink,
     pub destroyed: AtomicBool,
 }
 
@@ -51,12 +55,33 @@ impl MistyClientInner {
         self.destroyed.load(std::sync::atomic::Ordering::SeqCst)
     }
 
+    /// Enters a mut span of the states and the resources.
+    pub(crate) fn enter_mut_span(&self) {
+        self.state_manager.enter_mut_span();
+        self.resource_manager.enter_span();
+    }
+
+    /// Leaves the current mut span. Resources inserted in a rolled back span are discarded
+    /// after the states, which may hold them, are restored. States kept by a failed outermost
+    /// span with partial commit are not in any result, so the host is asked to flush them.
+    pub(crate) fn leave_mut_span(&self, failed: bool) -> bool {
+        let partial_commit = self.state_manager.is_partial_commit();
+        let outermost = self.state_manager.leave_mut_span(failed);
+        self.resource_manager.leave_span(failed && !partial_commit);
+        if outermost && failed && partial_commit && self.state_manager.has_updated_states() {
+            self.signal_emitter.emit(MistySignal::Schedule);
+        }
+        outermost
+    }
+
     pub fn destroy(&self) {
         self.destroyed
             .swap(true, std::sync::atomic::Ordering::SeqCst);
 
         self.async_task_pools
             .reset(self.async_task_runtime.as_ref());
+        self.schedule_manager.take_all_tasks();
+        self.schedule_manager.cancel_delayed(self);
     }
 }
 
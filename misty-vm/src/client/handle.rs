@@ -1,4 +1,7 @@
-use std::sync::{Arc, Weak};
+use std::{
+    sync::{Arc, Weak},
+    time::Duration,
+};
 
 use crate::resources::MistyResourceManager;
 
@@ -68,7 +71,58 @@ impl<'a> MistyReadonlyClientHandle<'a> {
     {
         self.inner
             .schedule_manager
-            .enqueue(&self.inner.signal_emitter, handler);
+            .enqueue(&self.inner.signal_emitter, None, handler);
+    }
+
+    /// Schedules the handler after `duration`. Cancelled if the client is destroyed before then.
+    pub fn schedule_after<E>(
+        &self,
+        duration: Duration,
+        handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
+    ) where
+        E: std::fmt::Display,
+    {
+        self.inner
+            .schedule_manager
+            .enqueue_after(self.inner, None, duration, handler);
+    }
+
+    /// Schedules the handler once no handler with the same tag is debounced for `duration`.
+    /// Only the last handler runs.
+    pub fn schedule_debounced<E>(
+        &self,
+        tag: impl ToString,
+        duration: Duration,
+        handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
+    ) where
+        E: std::fmt::Display,
+    {
+        self.inner.schedule_manager.enqueue_debounced(
+            self.inner,
+            None,
+            tag.to_string(),
+            duration,
+            handler,
+        );
+    }
+
+    /// Schedules at most one handler with the same tag every `duration`. The first handler runs
+    /// at once, and the last one throttled during the window runs when it ends.
+    pub fn schedule_throttled<E>(
+        &self,
+        tag: impl ToString,
+        duration: Duration,
+        handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
+    ) where
+        E: std::fmt::Display,
+    {
+        self.inner.schedule_manager.enqueue_throttled(
+            self.inner,
+            None,
+            tag.to_string(),
+            duration,
+            handler,
+        );
     }
 
     pub fn accessor(&self) -> MistyClientAccessor {
@@ -1,5 +1,4 @@
 use std::{
-    any::Any,
     convert::Infallible,
     marker::PhantomData,
     sync::{atomic::AtomicBool, Arc, RwLock},
@@ -8,28 +7,38 @@ use std::{
 use once_cell::sync::Lazy;
 
 use crate::{
-    async_task::{IAsyncTaskRuntimeAdapter, MistyAsyncTaskPools},
-    controllers::{call_controller, ControllerRet, MistyController},
+    async_task::{IAsyncTaskRuntimeAdapter, MistyAsyncTaskPools, MistyLiveAsyncTasks},
+    client::MistyClientHandle,
+    controllers::{
+        call_controller, call_controller_async, ControllerRet, MistyAsyncController,
+        MistyAsyncControllerError, MistyController, MistyControllerError,
+    },
+    errors::{MistyErrorEvent, MistyErrorSink},
+    middlewares::{MistyMiddleware, MistyMiddlewareManager},
+    registry::MistyControllerRegistry,
     resources::MistyResourceManager,
     schedule::{controller_flush_scheduled_tasks, ScheduleManager},
     services::MistyServiceManager,
     signals::{MistySignal, SignalEmitter},
-    states::MistyStateManager,
-    views::MistyViewModelManager,
+    states::{controller_restore_states, MistyStateManager, MistyStateSnapshotError},
+    views::{MistyViewModelManager, MistyViewTrait},
 };
 
 use super::{MistyClientAccessor, MistyClientId, MistyClientInner};
 
-struct MistyClient<R> {
+/// An owned client. Unlike [`SingletonMistyClientPod`], any number of clients can coexist,
+/// each with its own states, services and async task pools.
+pub struct MistyClient<R> {
     inner: Arc<MistyClientInner>,
+    controller_registry: RwLock<Option<Arc<MistyControllerRegistry<R>>>>,
     _marker: PhantomData<R>,
 }
 
 impl<R> MistyClient<R>
 where
-    R: Any + Default + Send + Sync + 'static,
+    R: MistyViewTrait,
 {
-    fn new(
+    pub fn new(
         view_manager: MistyViewModelManager<R>,
         state_manager: MistyStateManager,
         service_manager: MistyServiceManager,
@@ -45,14 +54,131 @@ where
             async_task_runtime: Box::new(async_task_runtime),
             schedule_manager: ScheduleManager::new(),
             signal_emitter: SignalEmitter::new(),
+            middleware_manager: MistyMiddlewareManager::new(),
+            error_sink: MistyErrorSink::new(),
             destroyed: AtomicBool::new(false),
         });
 
         Self {
             inner,
+            controller_registry: Default::default(),
             _marker: Default::default(),
         }
     }
+
+    pub fn id(&self) -> MistyClientId {
+        self.inner.id
+    }
+
+    pub fn call_controller<Controller, Arg, E, T>(
+        &self,
+        controller: Controller,
+        arg: Arg,
+    ) -> Result<ControllerRet<R, T>, MistyControllerError<E>>
+    where
+        Controller: MistyController<Arg, E, T>,
+    {
+        call_controller(&self.inner, controller, arg)
+    }
+
+    /// Runs an async controller on the runtime of the client. View changes of its scheduled
+    /// handlers are returned by `flush_scheduled_tasks` as usual.
+    pub async fn call_controller_async<Controller, Arg, E>(
+        &self,
+        controller: Controller,
+        arg: Arg,
+    ) -> Result<(), MistyAsyncControllerError<E>>
+    where
+        Controller: MistyAsyncController<Arg, E>,
+        E: Send + 'static,
+    {
+        call_controller_async(&self.inner, controller, arg).await
+    }
+
+    /// Replaces the controllers callable by [`MistyClient::call_controller_by_name`].
+    pub fn set_controller_registry(&self, registry: MistyControllerRegistry<R>) {
+        *self.controller_registry.write().unwrap() = Some(Arc::new(registry));
+    }
+
+    /// Calls a registered controller with an encoded argument, and returns the encoded
+    /// [`MistyWireRet`](crate::wire::MistyWireRet), which is an `Err` if the call failed.
+    pub fn call_controller_by_name(&self, name: &str, arg: &[u8]) -> Vec<u8> {
+        self.controller_registry(name).call(&self.inner, name, arg)
+    }
+
+    fn controller_registry(&self, name: &str) -> Arc<MistyControllerRegistry<R>> {
+        match self.controller_registry.read().unwrap().as_ref() {
+            Some(registry) => registry.clone(),
+            None => panic!("controller registry is not set. controller is {}", name),
+        }
+    }
+
+    pub fn on_signal(&self, f: impl Fn(MistySignal) + Send + Sync + 'static) {
+        self.inner.signal_emitter.set(f);
+    }
+
+    /// Adds a middleware wrapping all following controller calls.
+    pub fn add_middleware(&self, middleware: impl MistyMiddleware) {
+        self.inner.middleware_manager.add(middleware);
+    }
+
+    /// Receives errors and panics of spawned tasks and scheduled handlers.
+    pub fn on_error(&self, f: impl Fn(&MistyErrorEvent) + Send + Sync + 'static) {
+        self.inner.error_sink.set_handler(f);
+    }
+
+    /// Maps errors into state updates. `f` is scheduled, so its updates are returned by the
+    /// next `flush_scheduled_tasks`.
+    pub fn on_error_update(
+        &self,
+        f: impl Fn(MistyClientHandle, &MistyErrorEvent) + Send + Sync + 'static,
+    ) {
+        self.inner.error_sink.set_updater(f);
+    }
+
+    pub fn flush_scheduled_tasks(
+        &self,
+    ) -> Result<ControllerRet<R>, MistyControllerError<Infallible>> {
+        self.call_controller(controller_flush_scheduled_tasks, ())
+    }
+
+    pub fn accessor(&self) -> MistyClientAccessor {
+        MistyClientAccessor {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+
+    pub fn snapshot(&self) -> Result<Vec<u8>, MistyStateSnapshotError> {
+        self.inner.state_manager.snapshot()
+    }
+
+    /// Restores persisted states, and returns the view of restored states.
+    pub fn restore(
+        &self,
+        buf: &[u8],
+    ) -> Result<ControllerRet<R>, MistyControllerError<MistyStateSnapshotError>> {
+        self.call_controller(controller_restore_states, buf)
+    }
+
+    /// Spawned tasks that are neither finished nor cancelled, grouped by task type.
+    pub fn live_async_tasks(&self) -> Vec<MistyLiveAsyncTasks> {
+        self.inner.async_task_pools.live_tasks(&self.inner)
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.inner.is_destroyed()
+    }
+
+    pub fn destroy(&self) {
+        self.inner.destroy();
+    }
+}
+
+impl<R> Drop for MistyClient<R> {
+    fn drop(&mut self) {
+        // spawned tasks hold the inner client, so abort them here or it is never released
+        self.inner.destroy();
+    }
 }
 
 pub struct SingletonMistyClientPod<R> {
@@ -61,7 +187,7 @@ pub struct SingletonMistyClientPod<R> {
 
 impl<R> SingletonMistyClientPod<R>
 where
-    R: Any + Default + Send + Sync + 'static,
+    R: MistyViewTrait,
 {
     pub const fn new() -> Self {
         Self {
@@ -102,13 +228,13 @@ where
         ));
     }
 
-    pub fn call_controller<Controller, Arg, E>(
+    pub fn call_controller<Controller, Arg, E, T>(
         &self,
         controller: Controller,
         arg: Arg,
-    ) -> Result<ControllerRet<R>, E>
+    ) -> Result<ControllerRet<R, T>, MistyControllerError<E>>
     where
-        Controller: MistyController<Arg, E>,
+        Controller: MistyController<Arg, E, T>,
     {
         let inner = {
             let pod = self.client.read().unwrap();
@@ -124,12 +250,62 @@ where
         call_controller(&inner, controller, arg)
     }
 
+    pub async fn call_controller_async<Controller, Arg, E>(
+        &self,
+        controller: Controller,
+        arg: Arg,
+    ) -> Result<(), MistyAsyncControllerError<E>>
+    where
+        Controller: MistyAsyncController<Arg, E>,
+        E: Send + 'static,
+    {
+        let inner = self.inner();
+        call_controller_async(&inner, controller, arg).await
+    }
+
+    pub fn set_controller_registry(&self, registry: MistyControllerRegistry<R>) {
+        let pod = self.client.read().unwrap();
+        pod.as_ref()
+            .expect("client not in singleton pod.")
+            .set_controller_registry(registry);
+    }
+
+    pub fn call_controller_by_name(&self, name: &str, arg: &[u8]) -> Vec<u8> {
+        let (inner, registry) = {
+            let pod = self.client.read().unwrap();
+            if let Some(client) = pod.as_ref() {
+                (client.inner.clone(), client.controller_registry(name))
+            } else {
+                panic!("client not in singleton pod. controller is {}", name);
+            }
+        };
+        registry.call(&inner, name, arg)
+    }
+
     pub fn on_signal(&self, f: impl Fn(MistySignal) + Send + Sync + 'static) {
         let inner = self.inner();
         inner.signal_emitter.set(f);
     }
 
-    pub fn flush_scheduled_tasks(&self) -> Result<ControllerRet<R>, Infallible> {
+    pub fn add_middleware(&self, middleware: impl MistyMiddleware) {
+        let inner = self.inner();
+        inner.middleware_manager.add(middleware);
+    }
+
+    pub fn on_error(&self, f: impl Fn(&MistyErrorEvent) + Send + Sync + 'static) {
+        self.inner().error_sink.set_handler(f);
+    }
+
+    pub fn on_error_update(
+        &self,
+        f: impl Fn(MistyClientHandle, &MistyErrorEvent) + Send + Sync + 'static,
+    ) {
+        self.inner().error_sink.set_updater(f);
+    }
+
+    pub fn flush_scheduled_tasks(
+        &self,
+    ) -> Result<ControllerRet<R>, MistyControllerError<Infallible>> {
         self.call_controller(controller_flush_scheduled_tasks, ())
     }
 
@@ -139,6 +315,22 @@ where
         }
     }
 
+    pub fn snapshot(&self) -> Result<Vec<u8>, MistyStateSnapshotError> {
+        self.inner().state_manager.snapshot()
+    }
+
+    pub fn restore(
+        &self,
+        buf: &[u8],
+    ) -> Result<ControllerRet<R>, MistyControllerError<MistyStateSnapshotError>> {
+        self.call_controller(controller_restore_states, buf)
+    }
+
+    pub fn live_async_tasks(&self) -> Vec<MistyLiveAsyncTasks> {
+        let inner = self.inner();
+        inner.async_task_pools.live_tasks(&inner)
+    }
+
     fn inner(&self) -> Arc<MistyClientInner> {
         let pod = self.client.read().unwrap();
         if let Some(client) = pod.as_ref() {
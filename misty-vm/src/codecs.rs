@@ -0,0 +1,54 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Encodes controller arguments and results crossing a host boundary.
+pub trait MistyCodec: Clone + Send + Sync + 'static {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, MistyCodecError>;
+    fn decode<T: DeserializeOwned>(&self, buf: &[u8]) -> Result<T, MistyCodecError>;
+}
+
+#[derive(Debug)]
+pub struct MistyCodecError(Box<dyn std::error::Error + Send + Sync>);
+
+impl MistyCodecError {
+    pub fn new(err: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self(Box::new(err))
+    }
+}
+
+impl std::fmt::Display for MistyCodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "codec error: {}", self.0)
+    }
+}
+
+impl std::error::Error for MistyCodecError {}
+
+/// A text codec. `serde_json` is always available, since state snapshots are JSON too.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MistyJsonCodec;
+
+impl MistyCodec for MistyJsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, MistyCodecError> {
+        serde_json::to_vec(value).map_err(MistyCodecError::new)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, buf: &[u8]) -> Result<T, MistyCodecError> {
+        serde_json::from_slice(buf).map_err(MistyCodecError::new)
+    }
+}
+
+/// A compact binary codec, for hosts that do not need to read results as text.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MistyBincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl MistyCodec for MistyBincodeCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, MistyCodecError> {
+        bincode::serialize(value).map_err(MistyCodecError::new)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, buf: &[u8]) -> Result<T, MistyCodecError> {
+        bincode::deserialize(buf).map_err(MistyCodecError::new)
+    }
+}
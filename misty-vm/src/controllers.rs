@@ -1,9 +1,22 @@
-use std::{any::Any, sync::Arc};
+use std::{
+    convert::Infallible,
+    fmt::Debug,
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use futures::{channel::oneshot, future::BoxFuture, FutureExt};
 
 use crate::{
-    client::{MistyClientHandle, MistyClientInner},
+    async_task::{MistyAsyncTaskContext, MistyAsyncTaskTrait, PendingSchedules},
+    client::{MistyClientHandle, MistyClientInner, MistyReadonlyClientHandle},
+    errors::{panic_message, report_error, MistyErrorEvent, MistyErrorKind, MistyErrorSource},
+    middlewares::{MistyControllerCall, MistyControllerCallEnd},
     resources::ResourceUpdateAction,
     states::GuardCleanupStatesForPanic,
+    views::{MistyViewTrait, ViewUpdate},
 };
 
 pub struct MistyControllerContext<'a> {
@@ -18,62 +31,313 @@ impl<'a> MistyControllerContext<'a> {
     pub fn handle(&self) -> MistyClientHandle {
         self.handle
     }
+
+    /// Keeps the state updates of this controller even if it returns `Err` or panics.
+    /// By default they are rolled back.
+    pub fn allow_partial_commit(&self) {
+        self.handle.inner.state_manager.set_partial_commit();
+    }
 }
 
-pub trait MistyController<Arg, E> {
-    fn call(&self, ctx: MistyControllerContext, arg: Arg) -> Result<(), E>;
+/// A controller. The `Ok` value is returned to the host in [`ControllerRet::value`].
+pub trait MistyController<Arg, E, T = ()> {
+    fn call(&self, ctx: MistyControllerContext, arg: Arg) -> Result<T, E>;
 }
 
-impl<Arg, E, F> MistyController<Arg, E> for F
+impl<Arg, E, T, F> MistyController<Arg, E, T> for F
 where
-    F: Fn(MistyControllerContext, Arg) -> Result<(), E>,
+    F: Fn(MistyControllerContext, Arg) -> Result<T, E>,
 {
-    fn call(&self, ctx: MistyControllerContext, arg: Arg) -> Result<(), E> {
+    fn call(&self, ctx: MistyControllerContext, arg: Arg) -> Result<T, E> {
         self(ctx, arg)
     }
 }
 
-pub struct ControllerRet<R> {
-    pub changed_view: Option<R>,
+/// A controller whose body can await. States are updated in handlers passed to
+/// `ctx.schedule`, and the call resolves after the body and all of those handlers finished.
+pub trait MistyAsyncController<Arg, E> {
+    fn call(&self, ctx: MistyAsyncTaskContext, arg: Arg) -> BoxFuture<'static, Result<(), E>>;
+}
+
+impl<Arg, E, F, Fut> MistyAsyncController<Arg, E> for F
+where
+    F: Fn(MistyAsyncTaskContext, Arg) -> Fut,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+{
+    fn call(&self, ctx: MistyAsyncTaskContext, arg: Arg) -> BoxFuture<'static, Result<(), E>> {
+        Box::pin(self(ctx, arg))
+    }
+}
+
+#[derive(Debug)]
+pub enum MistyAsyncControllerError<E> {
+    Failed(E),
+    /// The client was destroyed before the controller finished.
+    Cancelled,
+    /// The controller panicked with this message. The panic is reported to the error sink
+    /// like panics of spawned tasks.
+    Panicked(String),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for MistyAsyncControllerError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Failed(err) => write!(f, "{}", err),
+            Self::Cancelled => write!(f, "controller is cancelled"),
+            Self::Panicked(message) => write!(f, "controller panicked: {}", message),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for MistyAsyncControllerError<E> {}
+
+#[derive(Debug)]
+pub enum MistyControllerError<E> {
+    Failed(E),
+    /// A middleware rejected the call, and the controller was not run.
+    Rejected(String),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for MistyControllerError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Failed(err) => write!(f, "{}", err),
+            Self::Rejected(reason) => write!(f, "controller is rejected: {}", reason),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for MistyControllerError<E> {}
+
+struct AsyncControllerTask;
+impl MistyAsyncTaskTrait for AsyncControllerTask {}
+
+pub struct ControllerRet<R: MistyViewTrait, T = ()> {
+    pub changed_view: Option<R::Patch>,
     pub changed_resources: Vec<ResourceUpdateAction>,
+    /// Order of results of outermost controller calls, starting from 1. Results of nested
+    /// calls carry nothing and have `None`. Hosts that call controllers from several threads
+    /// should apply results in this order.
+    pub seq: Option<u64>,
+    pub value: T,
+}
+
+/// How a call shows its argument, value and error to middlewares.
+pub(crate) trait DescribeCall<Arg, T, E> {
+    fn arg(arg: &Arg) -> Option<&dyn Debug>;
+    fn value(value: &T) -> Option<&dyn Debug>;
+    fn error(err: &E) -> Option<&dyn Debug>;
 }
 
-pub(crate) fn call_controller<R, Controller, Arg, E>(
+/// Shows type names only, so a call does not require `Debug`.
+pub(crate) struct Opaque;
+
+impl<Arg, T, E> DescribeCall<Arg, T, E> for Opaque {
+    fn arg(_arg: &Arg) -> Option<&dyn Debug> {
+        None
+    }
+    fn value(_value: &T) -> Option<&dyn Debug> {
+        None
+    }
+    fn error(_err: &E) -> Option<&dyn Debug> {
+        None
+    }
+}
+
+pub(crate) struct Described;
+
+impl<Arg: Debug, T: Debug, E: Debug> DescribeCall<Arg, T, E> for Described {
+    fn arg(arg: &Arg) -> Option<&dyn Debug> {
+        Some(arg)
+    }
+    fn value(value: &T) -> Option<&dyn Debug> {
+        Some(value)
+    }
+    fn error(err: &E) -> Option<&dyn Debug> {
+        Some(err)
+    }
+}
+
+pub(crate) fn call_controller<R, Controller, Arg, E, T>(
     inner: &Arc<MistyClientInner>,
     controller: Controller,
     arg: Arg,
-) -> Result<ControllerRet<R>, E>
+) -> Result<ControllerRet<R, T>, MistyControllerError<E>>
 where
-    R: Any + Default + Send + Sync + 'static,
-    Controller: MistyController<Arg, E>,
+    R: MistyViewTrait,
+    Controller: MistyController<Arg, E, T>,
 {
     let controller_name = std::any::type_name::<Controller>();
+    call_named_controller::<R, _, _, _, _, Opaque>(inner, controller_name, controller, arg)
+}
+
+/// Like [`call_controller`], with the name middlewares and logs see for the controller.
+pub(crate) fn call_named_controller<R, Controller, Arg, E, T, D>(
+    inner: &Arc<MistyClientInner>,
+    controller_name: &'static str,
+    controller: Controller,
+    arg: Arg,
+) -> Result<ControllerRet<R, T>, MistyControllerError<E>>
+where
+    R: MistyViewTrait,
+    Controller: MistyController<Arg, E, T>,
+    D: DescribeCall<Arg, T, E>,
+{
+    let middlewares = inner.middleware_manager.all();
+    if middlewares.is_empty() {
+        return run_controller(inner, controller_name, controller, arg)
+            .map_err(MistyControllerError::Failed);
+    }
+
+    {
+        let call = MistyControllerCall {
+            name: controller_name,
+            arg_type: std::any::type_name::<Arg>(),
+            arg: D::arg(&arg),
+        };
+        for middleware in middlewares.iter() {
+            middleware
+                .before_call(&call)
+                .map_err(MistyControllerError::Rejected)?;
+        }
+    }
+
+    let start = Instant::now();
+    let res = run_controller(inner, controller_name, controller, arg);
+    let elapsed = start.elapsed();
+    let end = match res.as_ref() {
+        Ok(ret) => MistyControllerCallEnd {
+            name: controller_name,
+            elapsed,
+            seq: ret.seq,
+            changed_view: ret
+                .changed_view
+                .as_ref()
+                .map(|patch| patch as &dyn std::any::Any),
+            changed_resources: &ret.changed_resources,
+            value: D::value(&ret.value),
+            error_type: None,
+            error: None,
+        },
+        Err(err) => MistyControllerCallEnd {
+            name: controller_name,
+            elapsed,
+            seq: None,
+            changed_view: None,
+            changed_resources: &[],
+            value: None,
+            error_type: Some(std::any::type_name::<E>()),
+            error: D::error(err),
+        },
+    };
+    for middleware in middlewares.iter() {
+        middleware.after_call(&end);
+    }
+    res.map_err(MistyControllerError::Failed)
+}
+
+fn run_controller<R, Controller, Arg, E, T>(
+    inner: &Arc<MistyClientInner>,
+    controller_name: &'static str,
+    controller: Controller,
+    arg: Arg,
+) -> Result<ControllerRet<R, T>, E>
+where
+    R: MistyViewTrait,
+    Controller: MistyController<Arg, E, T>,
+{
     let span = tracing::span!(tracing::Level::DEBUG, "call controller", controller_name);
     let _span_guard = span.enter();
 
     let mut _cleanup_guard = GuardCleanupStatesForPanic::new(Arc::downgrade(inner));
 
     let ctx = MistyControllerContext::new(MistyClientHandle { inner: &inner });
-    inner.state_manager.enter_mut_span();
+    inner.enter_mut_span();
     let res = controller.call(ctx, arg);
-    let can_notify = inner.state_manager.leave_mut_span();
+    let can_notify = _cleanup_guard.leave_mut_span(inner, res.is_err());
 
-    let mut changed_view: Option<R> = None;
+    let mut changed_view: Option<R::Patch> = None;
     let mut changed_actions: Vec<ResourceUpdateAction> = Default::default();
+    let mut seq: Option<u64> = None;
 
-    if can_notify {
-        changed_actions = inner.resource_manager.take_all_actions();
-        changed_view = Some(inner.view_manager.build_view(&inner).cast::<R>());
+    if can_notify && res.is_ok() {
+        let update = inner
+            .view_manager
+            .build_view(&inner)
+            .cast::<ViewUpdate<R::Patch>>();
+        changed_view = update.patch;
+        changed_actions = update.changed_resources;
+        seq = Some(update.seq);
         inner.state_manager.clear_updated_states();
     }
 
     _cleanup_guard.mark();
 
-    if let Err(e) = res {
-        return Err(e);
-    }
+    let value = res?;
     Ok(ControllerRet {
         changed_view,
         changed_resources: changed_actions,
+        seq,
+        value,
     })
 }
+
+pub(crate) async fn call_controller_async<Controller, Arg, E>(
+    inner: &Arc<MistyClientInner>,
+    controller: Controller,
+    arg: Arg,
+) -> Result<(), MistyAsyncControllerError<E>>
+where
+    Controller: MistyAsyncController<Arg, E>,
+    E: Send + 'static,
+{
+    let controller_name = std::any::type_name::<Controller>();
+    tracing::debug!("call async controller {}", controller_name);
+
+    let pending_schedules: PendingSchedules = Arc::new(Mutex::new(Default::default()));
+    let ctx = MistyAsyncTaskContext::tracked(Arc::downgrade(inner), pending_schedules.clone());
+    let future = controller.call(ctx, arg);
+
+    // spawned in a task pool, so it is aborted when the client is destroyed
+    let (tx, rx) = oneshot::channel();
+    let pool = inner.async_task_pools.get::<AsyncControllerTask>();
+    let weak_inner = Arc::downgrade(inner);
+    pool.spawn(
+        MistyReadonlyClientHandle { inner },
+        None,
+        move |_ctx| async move {
+            let res = match AssertUnwindSafe(future).catch_unwind().await {
+                Ok(res) => res.map_err(MistyAsyncControllerError::Failed),
+                Err(payload) => {
+                    let message = panic_message(payload.as_ref());
+                    tracing::error!("async controller panic");
+                    // reported before the call resolves, like panics of spawned tasks
+                    if let Some(inner) = weak_inner.upgrade() {
+                        let event = MistyErrorEvent {
+                            source: MistyErrorSource::Spawn,
+                            task_type: Some(std::any::type_name::<AsyncControllerTask>()),
+                            kind: MistyErrorKind::Panic(message.clone()),
+                        };
+                        report_error(&inner, event, true);
+                    }
+                    let _ = tx.send(Err(MistyAsyncControllerError::Panicked(message)));
+                    return Ok(());
+                }
+            };
+            loop {
+                let schedules = std::mem::take(&mut *pending_schedules.lock().unwrap());
+                if schedules.is_empty() {
+                    break;
+                }
+                for schedule in schedules.into_iter() {
+                    let _ = schedule.await;
+                }
+            }
+            let _ = tx.send(res);
+            Ok::<(), Infallible>(())
+        },
+    );
+
+    rx.await
+        .unwrap_or(Err(MistyAsyncControllerError::Cancelled))
+}
@@ -0,0 +1,88 @@
+use std::{
+    any::Any,
+    sync::{Arc, RwLock},
+};
+
+use crate::client::{MistyClientHandle, MistyClientInner};
+
+/// Where a reported error comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MistyErrorSource {
+    Spawn,
+    Schedule,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MistyErrorKind {
+    /// The `Display` of an error returned by the task or handler.
+    Error(String),
+    /// The payload of a panic, when it is a string.
+    Panic(String),
+}
+
+/// An error or panic of a spawned task or a scheduled handler.
+#[derive(Debug, Clone)]
+pub struct MistyErrorEvent {
+    pub source: MistyErrorSource,
+    /// Type name of the async task that failed or scheduled the handler. `None` for handlers
+    /// scheduled outside async tasks.
+    pub task_type: Option<&'static str>,
+    pub kind: MistyErrorKind,
+}
+
+type ErrorHandler = Arc<dyn Fn(&MistyErrorEvent) + Send + Sync>;
+type ErrorUpdater = Arc<dyn Fn(MistyClientHandle, &MistyErrorEvent) + Send + Sync>;
+
+pub(crate) struct MistyErrorSink {
+    handler: RwLock<Option<ErrorHandler>>,
+    updater: RwLock<Option<ErrorUpdater>>,
+}
+
+impl MistyErrorSink {
+    pub fn new() -> Self {
+        Self {
+            handler: Default::default(),
+            updater: Default::default(),
+        }
+    }
+
+    pub fn set_handler(&self, f: impl Fn(&MistyErrorEvent) + Send + Sync + 'static) {
+        *self.handler.write().unwrap() = Some(Arc::new(f));
+    }
+
+    pub fn set_updater(
+        &self,
+        f: impl Fn(MistyClientHandle, &MistyErrorEvent) + Send + Sync + 'static,
+    ) {
+        *self.updater.write().unwrap() = Some(Arc::new(f));
+    }
+}
+
+/// Passes `event` to the error handler, and schedules the error updater. Errors of the updater
+/// are not updated again, or a failing updater would be scheduled forever.
+pub(crate) fn report_error(inner: &MistyClientInner, event: MistyErrorEvent, updatable: bool) {
+    let handler = inner.error_sink.handler.read().unwrap().clone();
+    if let Some(handler) = handler {
+        handler(&event);
+    }
+
+    if !updatable || inner.is_destroyed() {
+        return;
+    }
+    let updater = inner.error_sink.updater.read().unwrap().clone();
+    if let Some(updater) = updater {
+        inner
+            .schedule_manager
+            .enqueue_error_update(&inner.signal_emitter, move |handle| updater(handle, &event));
+    }
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
@@ -0,0 +1,284 @@
+//! A C ABI for hosts that cannot link Rust directly. The Rust side registers apps by name
+//! with [`register_app`], and hosts create clients of them with `misty_client_create`.
+//!
+//! Every result is a [`MistyWireRet`] encoded with the codec of the app. The header is at
+//! `include/misty_vm.h`.
+
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_void, CStr},
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::{
+    client::MistyClient,
+    codecs::MistyCodec,
+    registry::MistyDispatchError,
+    signals::MistySignal,
+    views::MistyViewTrait,
+    wire::{MistyWireError, MistyWireRet},
+};
+
+type AppBuilder = Arc<dyn Fn() -> Box<dyn FfiClient> + Send + Sync>;
+
+static APPS: Lazy<RwLock<HashMap<String, AppBuilder>>> = Lazy::new(Default::default);
+
+trait FfiClient: Send + Sync {
+    fn call(&self, name: &str, arg: &[u8]) -> Vec<u8>;
+    fn flush_scheduled_tasks(&self) -> Vec<u8>;
+    fn on_signal(&self, f: Box<dyn Fn(MistySignal) + Send + Sync>);
+    fn destroy(&self);
+}
+
+struct CodecClient<R, C> {
+    client: MistyClient<R>,
+    codec: C,
+}
+
+impl<R, C> FfiClient for CodecClient<R, C>
+where
+    R: MistyViewTrait,
+    R::Patch: Serialize,
+    C: MistyCodec,
+{
+    fn call(&self, name: &str, arg: &[u8]) -> Vec<u8> {
+        self.client.call_controller_by_name(name, arg)
+    }
+
+    fn flush_scheduled_tasks(&self) -> Vec<u8> {
+        // flushing fails only when a middleware rejects it
+        let ret = match self.client.flush_scheduled_tasks() {
+            Ok(ret) => MistyWireRet::from(ret),
+            Err(err) => MistyWireRet::err(MistyWireError::from(&MistyDispatchError::from(err))),
+        };
+        self.encode(&ret)
+    }
+
+    fn on_signal(&self, f: Box<dyn Fn(MistySignal) + Send + Sync>) {
+        self.client.on_signal(f);
+    }
+
+    fn destroy(&self) {
+        self.client.destroy();
+    }
+}
+
+impl<R, C> CodecClient<R, C>
+where
+    C: MistyCodec,
+{
+    fn encode<T: Serialize>(&self, ret: &T) -> Vec<u8> {
+        match self.codec.encode(ret) {
+            Ok(buf) => buf,
+            Err(err) => {
+                tracing::error!("fail to encode controller result: {}", err);
+                Default::default()
+            }
+        }
+    }
+}
+
+/// Registers an app, so hosts can create clients of it by `name`. `build` is called for every
+/// created client, and must set the controller registry of the client.
+pub fn register_app<R, C>(
+    name: impl Into<String>,
+    codec: C,
+    build: impl Fn() -> MistyClient<R> + Send + Sync + 'static,
+) where
+    R: MistyViewTrait,
+    R::Patch: Serialize,
+    C: MistyCodec,
+{
+    let name = name.into();
+    let mut apps = APPS.write().unwrap();
+    if apps.contains_key(&name) {
+        panic!("app {} is registered twice", name);
+    }
+    apps.insert(
+        name,
+        Arc::new(move || {
+            Box::new(CodecClient {
+                client: build(),
+                codec: codec.clone(),
+            })
+        }),
+    );
+}
+
+/// A client created by `misty_client_create`.
+pub struct MistyFfiClient {
+    client: Box<dyn FfiClient>,
+    scheduled: Arc<AtomicBool>,
+}
+
+/// A buffer owned by Rust. It must be released by `misty_buffer_free`.
+#[repr(C)]
+pub struct MistyFfiBuffer {
+    pub ptr: *mut u8,
+    pub len: usize,
+    pub cap: usize,
+}
+
+impl From<Vec<u8>> for MistyFfiBuffer {
+    fn from(buf: Vec<u8>) -> Self {
+        let mut buf = std::mem::ManuallyDrop::new(buf);
+        Self {
+            ptr: buf.as_mut_ptr(),
+            len: buf.len(),
+            cap: buf.capacity(),
+        }
+    }
+}
+
+/// Called from any thread when handlers are scheduled. The host should call
+/// `misty_client_flush_scheduled_tasks` on its own thread, not inside the callback.
+pub type MistyScheduleCallback = Option<unsafe extern "C" fn(user_data: *mut c_void)>;
+
+struct UserData(*mut c_void);
+
+// the host is responsible for the user data being usable from any thread
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+impl UserData {
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+fn catch_buffer(f: impl FnOnce() -> Vec<u8>) -> MistyFfiBuffer {
+    match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(buf) => buf.into(),
+        Err(_) => {
+            tracing::error!("controller panic");
+            Vec::new().into()
+        }
+    }
+}
+
+/// Creates a client of a registered app. Returns null if `app_name` is not registered.
+///
+/// # Safety
+///
+/// `app_name` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn misty_client_create(
+    app_name: *const c_char,
+    on_schedule: MistyScheduleCallback,
+    user_data: *mut c_void,
+) -> *mut MistyFfiClient {
+    if app_name.is_null() {
+        return std::ptr::null_mut();
+    }
+    let Ok(app_name) = CStr::from_ptr(app_name).to_str() else {
+        return std::ptr::null_mut();
+    };
+    let Some(build) = APPS.read().unwrap().get(app_name).cloned() else {
+        return std::ptr::null_mut();
+    };
+    let Ok(client) = std::panic::catch_unwind(AssertUnwindSafe(|| build())) else {
+        tracing::error!("fail to build app {}", app_name);
+        return std::ptr::null_mut();
+    };
+
+    let scheduled: Arc<AtomicBool> = Default::default();
+    {
+        let scheduled = scheduled.clone();
+        let user_data = UserData(user_data);
+        client.on_signal(Box::new(move |signal| match signal {
+            MistySignal::Schedule => {
+                scheduled.store(true, Ordering::SeqCst);
+                if let Some(on_schedule) = on_schedule {
+                    on_schedule(user_data.get());
+                }
+            }
+        }));
+    }
+
+    Box::into_raw(Box::new(MistyFfiClient { client, scheduled }))
+}
+
+/// Calls the controller registered as `controller` with an encoded argument. An empty buffer
+/// is returned if the controller panics.
+///
+/// # Safety
+///
+/// `client` must be created by `misty_client_create` and not destroyed. `controller` must be a
+/// valid nul-terminated string, and `arg` must point to `arg_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn misty_client_call(
+    client: *const MistyFfiClient,
+    controller: *const c_char,
+    arg: *const u8,
+    arg_len: usize,
+) -> MistyFfiBuffer {
+    let client = &*client;
+    let controller = CStr::from_ptr(controller).to_string_lossy();
+    let arg = if arg_len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(arg, arg_len)
+    };
+    catch_buffer(|| client.client.call(&controller, arg))
+}
+
+/// Runs scheduled handlers, and returns the encoded view and resource updates.
+///
+/// # Safety
+///
+/// `client` must be created by `misty_client_create` and not destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn misty_client_flush_scheduled_tasks(
+    client: *const MistyFfiClient,
+) -> MistyFfiBuffer {
+    let client = &*client;
+    client.scheduled.store(false, Ordering::SeqCst);
+    catch_buffer(|| client.client.flush_scheduled_tasks())
+}
+
+/// Like `misty_client_flush_scheduled_tasks`, but returns an empty buffer without flushing if
+/// nothing is scheduled since the last flush.
+///
+/// # Safety
+///
+/// `client` must be created by `misty_client_create` and not destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn misty_client_poll(client: *const MistyFfiClient) -> MistyFfiBuffer {
+    let client = &*client;
+    if !client.scheduled.swap(false, Ordering::SeqCst) {
+        return Vec::new().into();
+    }
+    catch_buffer(|| client.client.flush_scheduled_tasks())
+}
+
+/// Destroys a client and releases it.
+///
+/// # Safety
+///
+/// `client` must be created by `misty_client_create`, and is invalid after the call.
+#[no_mangle]
+pub unsafe extern "C" fn misty_client_destroy(client: *mut MistyFfiClient) {
+    if client.is_null() {
+        return;
+    }
+    let client = Box::from_raw(client);
+    client.client.destroy();
+}
+
+/// # Safety
+///
+/// `buf` must be returned by this library and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn misty_buffer_free(buf: MistyFfiBuffer) {
+    if buf.ptr.is_null() {
+        return;
+    }
+    drop(Vec::from_raw_parts(buf.ptr, buf.len, buf.cap));
+}
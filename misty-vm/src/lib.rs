@@ -1,13 +1,25 @@
 pub mod async_task;
 pub mod client;
+pub mod codecs;
 pub mod controllers;
+pub mod errors;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod middlewares;
+pub mod registry;
 pub mod resources;
+pub mod retry;
+#[cfg(any(feature = "tokio", feature = "futures-executor"))]
+pub mod runtimes;
 pub mod schedule;
 pub mod services;
 pub mod signals;
 pub mod states;
+pub mod timer;
+pub mod undo;
 pub(crate) mod utils;
 pub mod views;
+pub mod wire;
 
 pub use futures::future::{BoxFuture, LocalBoxFuture};
-pub use misty_vm_macro::{misty_service, misty_states, MistyAsyncTask, MistyState};
+pub use misty_vm_macro::{misty_service, misty_states, MistyAsyncTask, MistyState, MistyView};
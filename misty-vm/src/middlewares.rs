@@ -0,0 +1,75 @@
+use std::{
+    any::Any,
+    fmt::Debug,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use crate::resources::ResourceUpdateAction;
+
+/// A controller call seen by middlewares before the controller runs.
+///
+/// Controllers called directly need not have `Debug` arguments, values or errors, so only
+/// calls dispatched by name through a
+/// [`MistyControllerRegistry`](crate::registry::MistyControllerRegistry) carry them.
+pub struct MistyControllerCall<'a> {
+    /// Type name of the controller.
+    pub name: &'static str,
+    /// Type name of the argument.
+    pub arg_type: &'static str,
+    /// The argument, for calls dispatched through a registry.
+    pub arg: Option<&'a dyn Debug>,
+}
+
+/// A finished controller call.
+pub struct MistyControllerCallEnd<'a> {
+    pub name: &'static str,
+    pub elapsed: Duration,
+    /// [`ControllerRet::seq`](crate::controllers::ControllerRet::seq) of the result. Always
+    /// `None` for failed calls.
+    pub seq: Option<u64>,
+    /// The patch of the result, which downcasts to the `Patch` of the view. `None` if the view
+    /// did not change or the call failed.
+    pub changed_view: Option<&'a dyn Any>,
+    /// Resource actions of the result. Empty for failed calls.
+    pub changed_resources: &'a [ResourceUpdateAction],
+    /// The value of the result, for calls dispatched through a registry.
+    pub value: Option<&'a dyn Debug>,
+    /// Type name of the error, or `None` if the controller returned `Ok`.
+    pub error_type: Option<&'static str>,
+    /// The error, for calls dispatched through a registry.
+    pub error: Option<&'a dyn Debug>,
+}
+
+/// Wraps every controller call of a client, including `flush_scheduled_tasks`.
+pub trait MistyMiddleware: Send + Sync + 'static {
+    /// Returning `Err` rejects the call, and the controller is not run.
+    fn before_call(&self, _call: &MistyControllerCall) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called after the controller returned. Not called for rejected calls.
+    fn after_call(&self, _call: &MistyControllerCallEnd) {}
+}
+
+pub(crate) struct MistyMiddlewareManager {
+    middlewares: RwLock<Vec<Arc<dyn MistyMiddleware>>>,
+}
+
+impl MistyMiddlewareManager {
+    pub fn new() -> Self {
+        Self {
+            middlewares: Default::default(),
+        }
+    }
+
+    pub fn add(&self, middleware: impl MistyMiddleware) {
+        let mut w = self.middlewares.write().unwrap();
+        w.push(Arc::new(middleware));
+    }
+
+    /// Middlewares in the order they were added. Cloned, so a middleware can add another one.
+    pub fn all(&self) -> Vec<Arc<dyn MistyMiddleware>> {
+        self.middlewares.read().unwrap().clone()
+    }
+}
@@ -0,0 +1,158 @@
+use std::{collections::HashMap, marker::PhantomData, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    client::MistyClientInner,
+    codecs::{MistyCodec, MistyCodecError},
+    controllers::{
+        call_named_controller, Described, MistyController, MistyControllerContext,
+        MistyControllerError,
+    },
+    views::MistyViewTrait,
+    wire::{MistyWireError, MistyWireRet},
+};
+
+type NamedController =
+    Box<dyn Fn(&Arc<MistyClientInner>, &[u8]) -> Result<Vec<u8>, MistyDispatchError> + Send + Sync>;
+
+type ErrorEncoder = Arc<dyn Fn(&MistyDispatchError) -> Vec<u8> + Send + Sync>;
+
+/// Controllers registered under names, so hosts that cannot name Rust functions can call them
+/// with encoded arguments.
+pub struct MistyControllerRegistry<R> {
+    controllers: HashMap<String, NamedController>,
+    encode_error: ErrorEncoder,
+    _marker: PhantomData<R>,
+}
+
+pub struct MistyControllerRegistryBuilder<R, C> {
+    codec: C,
+    controllers: HashMap<String, NamedController>,
+    _marker: PhantomData<R>,
+}
+
+#[derive(Debug)]
+pub enum MistyDispatchError {
+    UnknownController(String),
+    Codec(MistyCodecError),
+    /// The controller returned `Err`, formatted with `Display`.
+    Failed(String),
+    Rejected(String),
+}
+
+impl std::fmt::Display for MistyDispatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownController(name) => write!(f, "unknown controller {}", name),
+            Self::Codec(err) => write!(f, "{}", err),
+            Self::Failed(err) => write!(f, "{}", err),
+            Self::Rejected(reason) => write!(f, "controller is rejected: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MistyDispatchError {}
+
+impl From<MistyCodecError> for MistyDispatchError {
+    fn from(err: MistyCodecError) -> Self {
+        Self::Codec(err)
+    }
+}
+
+impl<E: std::fmt::Display> From<MistyControllerError<E>> for MistyDispatchError {
+    fn from(err: MistyControllerError<E>) -> Self {
+        match err {
+            MistyControllerError::Failed(err) => Self::Failed(err.to_string()),
+            MistyControllerError::Rejected(reason) => Self::Rejected(reason),
+        }
+    }
+}
+
+impl<R> MistyControllerRegistry<R>
+where
+    R: MistyViewTrait,
+    R::Patch: Serialize,
+{
+    /// Arguments are decoded with `codec`, and results are encoded as [`MistyWireRet`].
+    pub fn builder<C: MistyCodec>(codec: C) -> MistyControllerRegistryBuilder<R, C> {
+        MistyControllerRegistryBuilder {
+            codec,
+            controllers: Default::default(),
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<R> MistyControllerRegistry<R> {
+    pub fn contains(&self, name: &str) -> bool {
+        self.controllers.contains_key(name)
+    }
+
+    /// Dispatch failures are encoded as the `Err` of a [`MistyWireRet`] too.
+    pub(crate) fn call(&self, inner: &Arc<MistyClientInner>, name: &str, arg: &[u8]) -> Vec<u8> {
+        let res = match self.controllers.get(name) {
+            Some(controller) => controller(inner, arg),
+            None => Err(MistyDispatchError::UnknownController(name.to_string())),
+        };
+        res.unwrap_or_else(|err| (self.encode_error)(&err))
+    }
+}
+
+/// The `Err` of a [`MistyWireRet`] is encoded the same for any patch and value type.
+fn encode_error<C: MistyCodec>(codec: &C, err: &MistyDispatchError) -> Vec<u8> {
+    codec
+        .encode(&MistyWireRet::<(), ()>::err(MistyWireError::from(err)))
+        .expect("[Internal Error] fail to encode dispatch error")
+}
+
+impl<R, C> MistyControllerRegistryBuilder<R, C>
+where
+    R: MistyViewTrait,
+    R::Patch: Serialize,
+    C: MistyCodec,
+{
+    pub fn register<Controller, Arg, E, T>(
+        mut self,
+        name: impl Into<String>,
+        controller: Controller,
+    ) -> Self
+    where
+        Controller: MistyController<Arg, E, T> + Send + Sync + 'static,
+        Arg: DeserializeOwned + std::fmt::Debug + 'static,
+        E: std::fmt::Debug + std::fmt::Display + 'static,
+        T: Serialize + std::fmt::Debug + 'static,
+    {
+        let name = name.into();
+        if self.controllers.contains_key(&name) {
+            panic!("controller {} is registered twice", name);
+        }
+
+        let codec = self.codec.clone();
+        let controller_name = std::any::type_name::<Controller>();
+        self.controllers.insert(
+            name,
+            Box::new(move |inner, buf| {
+                let arg: Arg = codec.decode(buf)?;
+                let ret = call_named_controller::<R, _, _, _, _, Described>(
+                    inner,
+                    controller_name,
+                    |ctx: MistyControllerContext, arg: Arg| controller.call(ctx, arg),
+                    arg,
+                )?;
+                let buf = codec.encode(&MistyWireRet::from(ret))?;
+                Ok(buf)
+            }),
+        );
+        self
+    }
+
+    pub fn build(self) -> MistyControllerRegistry<R> {
+        let codec = self.codec;
+        MistyControllerRegistry {
+            controllers: self.controllers,
+            encode_error: Arc::new(move |err| encode_error(&codec, err)),
+            _marker: Default::default(),
+        }
+    }
+}
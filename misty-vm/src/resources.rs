@@ -1,4 +1,5 @@
 use std::{
+    cell::RefCell,
     collections::HashMap,
     fmt::Debug,
     ops::Deref,
@@ -6,7 +7,9 @@ use std::{
 };
 
 use serde::{Deserialize, Serialize};
+use thread_local::ThreadLocal;
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ResourceUpdateAction {
     Insert(MistyResourceId, Vec<u8>),
     Remove(MistyResourceId),
@@ -70,6 +73,8 @@ struct MistyResourceManagerStore {
 
 pub struct MistyResourceManager {
     store: Arc<MistyResourceManagerStore>,
+    /// Resources inserted in each mut span of the thread, discarded if the span rolls back.
+    frames: ThreadLocal<RefCell<Vec<Vec<MistyResourceId>>>>,
 }
 
 impl Debug for MistyResourceManager {
@@ -132,6 +137,7 @@ impl MistyResourceManager {
                 pending_actions: Arc::new(RwLock::new(Some(Default::default()))),
                 weak_map: Default::default(),
             }),
+            frames: Default::default(),
         }
     }
 
@@ -164,10 +170,77 @@ impl MistyResourceManager {
             let mut writter = self.store.weak_map.write().unwrap();
             writter.insert(id, Arc::downgrade(&ptr));
         }
+        if let Some(frame) = self.frames.get_or_default().borrow_mut().last_mut() {
+            frame.push(id);
+        }
 
         handle
     }
 
+    pub(crate) fn enter_span(&self) {
+        self.frames
+            .get_or_default()
+            .borrow_mut()
+            .push(Default::default());
+    }
+
+    /// Leaves the current mut span. If `rolled_back`, the resources inserted in the span are
+    /// never sent to the host, unless a state kept after the rollback still holds them.
+    pub(crate) fn leave_span(&self, rolled_back: bool) {
+        let ids = {
+            let mut frames = self.frames.get_or_default().borrow_mut();
+            let ids = frames
+                .pop()
+                .expect("[Internal Error] leave resource span without entering it");
+            match frames.last_mut() {
+                Some(parent) if !rolled_back => {
+                    parent.extend(ids);
+                    return;
+                }
+                _ => ids,
+            }
+        };
+        if rolled_back {
+            self.discard_inserts(ids);
+        }
+    }
+
+    fn discard_inserts(&self, ids: Vec<MistyResourceId>) {
+        let handles: Vec<MistyResourceHandle> = {
+            let mut writter = self.store.pending_actions.write().unwrap();
+            let writer = writter.as_mut().unwrap();
+            ids.into_iter()
+                .filter_map(|id| match writer.remove(&id) {
+                    Some(ToFlushResourceAction::Insert(handle)) => Some(handle),
+                    Some(action) => {
+                        writer.insert(id, action);
+                        None
+                    }
+                    None => None,
+                })
+                .collect()
+        };
+
+        for handle in handles {
+            match Arc::try_unwrap(handle.ptr) {
+                // never sent, so the host is not told of its removal either
+                Ok(mut inner) => {
+                    inner.store_ref = Weak::new();
+                    self.store.weak_map.write().unwrap().remove(&inner.id);
+                }
+                // still held, e.g. by a service
+                Err(ptr) => {
+                    let mut writter = self.store.pending_actions.write().unwrap();
+                    let writer = writter.as_mut().unwrap();
+                    writer.insert(
+                        ptr.id,
+                        ToFlushResourceAction::Insert(MistyResourceHandle { ptr }),
+                    );
+                }
+            }
+        }
+    }
+
     pub(crate) fn take_all_actions(&self) -> Vec<ResourceUpdateAction> {
         let pending_ids = {
             let mut writer = self.store.pending_actions.write().unwrap();
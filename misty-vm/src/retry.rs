@@ -0,0 +1,121 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use futures::future::{select, Either};
+
+use crate::async_task::MistyAsyncTaskContext;
+
+/// How a task spawned by `spawn_with_retry` runs again after failing.
+pub struct MistyRetryPolicy<E> {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    retry_if: Arc<dyn Fn(&E) -> bool + Send + Sync>,
+}
+
+impl<E> Clone for MistyRetryPolicy<E> {
+    fn clone(&self) -> Self {
+        Self {
+            max_attempts: self.max_attempts,
+            initial_backoff: self.initial_backoff,
+            max_backoff: self.max_backoff,
+            multiplier: self.multiplier,
+            jitter: self.jitter,
+            retry_if: self.retry_if.clone(),
+        }
+    }
+}
+
+impl<E> MistyRetryPolicy<E> {
+    /// Runs at most `max_attempts` times, waiting 100ms after the first failure and twice as
+    /// long after each next one, up to 10s, with a jitter of 0.2. All errors are retried.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retry_if: Arc::new(|_| true),
+        }
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Each delay is shortened by a random part of up to `jitter` of it, so clients failing
+    /// together do not retry together. Clamped to `0.0..=1.0`.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Only errors matching `f` are retried.
+    pub fn retry_if(mut self, f: impl Fn(&E) -> bool + Send + Sync + 'static) -> Self {
+        self.retry_if = Arc::new(f);
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// The delay after the failed `attempt`, counted from 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = (self.initial_backoff.as_secs_f64() * self.multiplier.powi(exp))
+            .min(self.max_backoff.as_secs_f64());
+        let delay = delay * (1.0 - self.jitter * random_unit());
+        Duration::from_secs_f64(delay.max(0.0))
+    }
+}
+
+/// A random number in `0.0..1.0`. Each `RandomState` is seeded differently.
+fn random_unit() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+
+    let bits = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+pub(crate) async fn run_with_retry<T, E>(
+    ctx: MistyAsyncTaskContext,
+    policy: MistyRetryPolicy<E>,
+    future_fn: impl Fn(MistyAsyncTaskContext) -> T,
+) -> Result<(), E>
+where
+    T: Future<Output = Result<(), E>>,
+    E: std::fmt::Display,
+{
+    let mut attempt = 1;
+    loop {
+        let err = match future_fn(ctx.clone()).await {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        if attempt >= policy.max_attempts || !(policy.retry_if)(&err) {
+            return Err(err);
+        }
+        let Some(sleep) = ctx.runtime_sleep(policy.backoff(attempt)) else {
+            return Err(err);
+        };
+        tracing::debug!("attempt {} fails, error: {}", attempt, err);
+
+        if let Either::Right(_) = select(sleep, ctx.cancelled()).await {
+            // the error is not reported, since the task is cancelled
+            return Ok(());
+        }
+        attempt += 1;
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{atomic::AtomicU64, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::future::{AbortHandle, AbortRegistration, Abortable, BoxFuture};
+
+use crate::async_task::{IAsyncTaskRuntimeAdapter, LocalFutureFn};
+
+/// Abort handles of spawned tasks. A finished task removes its own handle.
+#[derive(Debug, Default)]
+struct AbortableTasks {
+    alloc: AtomicU64,
+    handles: Arc<Mutex<HashMap<u64, AbortHandle>>>,
+}
+
+/// A task that is registered before its future is created.
+struct AbortableTask {
+    id: u64,
+    registration: AbortRegistration,
+    handles: Arc<Mutex<HashMap<u64, AbortHandle>>>,
+}
+
+impl AbortableTask {
+    fn wrap(self, future: impl Future<Output = ()>) -> impl Future<Output = ()> {
+        let Self {
+            id,
+            registration,
+            handles,
+        } = self;
+        let future = Abortable::new(future, registration);
+        async move {
+            let _ = future.await;
+            handles.lock().unwrap().remove(&id);
+        }
+    }
+}
+
+impl AbortableTasks {
+    fn register(&self) -> AbortableTask {
+        let id = self.alloc.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let (handle, registration) = AbortHandle::new_pair();
+        // inserted before the task is spawned, so it is never removed before being inserted
+        self.handles.lock().unwrap().insert(id, handle);
+        AbortableTask {
+            id,
+            registration,
+            handles: self.handles.clone(),
+        }
+    }
+
+    fn wrap(&self, future: impl Future<Output = ()>) -> (u64, impl Future<Output = ()>) {
+        let task = self.register();
+        (task.id, task.wrap(future))
+    }
+
+    fn abort(&self, id: u64) {
+        let handle = self.handles.lock().unwrap().remove(&id);
+        if let Some(handle) = handle {
+            handle.abort();
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.handles.lock().unwrap().len()
+    }
+}
+
+/// Spawns tasks on a tokio runtime. Local tasks run in a [`tokio::task::LocalSet`] of the
+/// adapter, on a thread it starts at the first `spawn_local`, and are dropped with the adapter.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct MistyTokioAsyncTaskAdapter {
+    handle: Option<tokio::runtime::Handle>,
+    tasks: AbortableTasks,
+    /// On the tokio clock, which is paused with the runtime in tests.
+    started: tokio::time::Instant,
+    local: std::sync::OnceLock<futures::channel::mpsc::UnboundedSender<LocalFutureFn>>,
+}
+
+#[cfg(feature = "tokio")]
+impl Default for MistyTokioAsyncTaskAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl MistyTokioAsyncTaskAdapter {
+    /// Spawns on the runtime of the caller, like [`tokio::spawn`].
+    pub fn new() -> Self {
+        Self {
+            handle: None,
+            tasks: Default::default(),
+            started: tokio::time::Instant::now(),
+            local: Default::default(),
+        }
+    }
+
+    /// Spawns on the runtime of `handle`, so controllers can be called outside the runtime.
+    pub fn with_handle(handle: tokio::runtime::Handle) -> Self {
+        let started = {
+            let _guard = handle.enter();
+            tokio::time::Instant::now()
+        };
+        Self {
+            handle: Some(handle),
+            tasks: Default::default(),
+            started,
+            local: Default::default(),
+        }
+    }
+
+    /// Starts the thread of local tasks. Its `LocalSet` is driven with the runtime of the
+    /// adapter, so local tasks share its timers, and ends when the adapter is dropped.
+    fn local_sender(&self) -> &futures::channel::mpsc::UnboundedSender<LocalFutureFn> {
+        use futures::StreamExt;
+
+        self.local.get_or_init(|| {
+            let handle = self
+                .handle
+                .clone()
+                .unwrap_or_else(tokio::runtime::Handle::current);
+            let (tx, mut rx) = futures::channel::mpsc::unbounded::<LocalFutureFn>();
+            std::thread::Builder::new()
+                .name("misty-local-tasks".to_string())
+                .spawn(move || {
+                    let local = tokio::task::LocalSet::new();
+                    handle.block_on(local.run_until(async move {
+                        while let Some(future_fn) = rx.next().await {
+                            tokio::task::spawn_local(future_fn());
+                        }
+                    }));
+                })
+                .expect("fail to start the thread of local tasks");
+            tx
+        })
+    }
+
+    /// Tasks that are neither finished nor aborted.
+    pub fn running_tasks(&self) -> usize {
+        self.tasks.len()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl IAsyncTaskRuntimeAdapter for MistyTokioAsyncTaskAdapter {
+    fn spawn(&self, future: BoxFuture<'static, ()>) -> u64 {
+        let (id, future) = self.tasks.wrap(future);
+        if let Some(handle) = self.handle.as_ref() {
+            handle.spawn(future);
+        } else {
+            tokio::spawn(future);
+        }
+        id
+    }
+
+    fn spawn_local(&self, future_fn: LocalFutureFn) -> u64 {
+        let task = self.tasks.register();
+        let id = task.id;
+        let future_fn: LocalFutureFn = Box::new(move || Box::pin(task.wrap(future_fn())));
+        if self.local_sender().unbounded_send(future_fn).is_err() {
+            tracing::error!("fail to spawn local task: the thread of local tasks is stopped");
+            self.tasks.abort(id);
+        }
+        id
+    }
+
+    fn try_abort(&self, task_id: u64) {
+        self.tasks.abort(task_id);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        // the timer is bound to the runtime current when it is created
+        let _guard = self.handle.as_ref().map(|handle| handle.enter());
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    fn now(&self) -> Duration {
+        let _guard = self.handle.as_ref().map(|handle| handle.enter());
+        self.started.elapsed()
+    }
+}
+
+#[cfg(feature = "futures-executor")]
+thread_local! {
+    static LOCAL_SPAWNER: std::cell::RefCell<Option<futures::executor::LocalSpawner>> =
+        Default::default();
+}
+
+/// Spawns tasks on a [`futures::executor::ThreadPool`]. `spawn_local` uses the spawner set on
+/// the calling thread by [`MistyFuturesAsyncTaskAdapter::set_local_spawner`].
+#[cfg(feature = "futures-executor")]
+#[derive(Debug)]
+pub struct MistyFuturesAsyncTaskAdapter {
+    pool: futures::executor::ThreadPool,
+    tasks: AbortableTasks,
+    started: Instant,
+}
+
+#[cfg(feature = "futures-executor")]
+impl MistyFuturesAsyncTaskAdapter {
+    pub fn new(pool: futures::executor::ThreadPool) -> Self {
+        Self {
+            pool,
+            tasks: Default::default(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Local tasks spawned on this thread are run by the `LocalPool` of `spawner`.
+    pub fn set_local_spawner(spawner: futures::executor::LocalSpawner) {
+        LOCAL_SPAWNER.with(|local| *local.borrow_mut() = Some(spawner));
+    }
+
+    /// Tasks that are neither finished nor aborted.
+    pub fn running_tasks(&self) -> usize {
+        self.tasks.len()
+    }
+}
+
+#[cfg(feature = "futures-executor")]
+impl IAsyncTaskRuntimeAdapter for MistyFuturesAsyncTaskAdapter {
+    fn spawn(&self, future: BoxFuture<'static, ()>) -> u64 {
+        let (id, future) = self.tasks.wrap(future);
+        self.pool.spawn_ok(future);
+        id
+    }
+
+    fn spawn_local(&self, future_fn: LocalFutureFn) -> u64 {
+        use futures::task::LocalSpawnExt;
+
+        let (id, future) = self.tasks.wrap(future_fn());
+        LOCAL_SPAWNER.with(|local| {
+            let local = local.borrow();
+            let spawner = local
+                .as_ref()
+                .expect("local spawner is not set on this thread");
+            if let Err(err) = spawner.spawn_local(future) {
+                tracing::error!("fail to spawn local task: {}", err);
+                self.tasks.abort(id);
+            }
+        });
+        id
+    }
+
+    fn try_abort(&self, task_id: u64) {
+        self.tasks.abort(task_id);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(futures_timer::Delay::new(duration))
+    }
+
+    fn now(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
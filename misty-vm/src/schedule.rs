@@ -1,24 +1,103 @@
 use std::{
+    collections::HashMap,
     convert::Infallible,
-    sync::{Arc, RwLock},
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
 };
 
+use futures::channel::oneshot;
+
 use crate::{
-    client::MistyClientHandle,
+    client::{MistyClientHandle, MistyClientInner},
     controllers::MistyControllerContext,
+    errors::{panic_message, report_error, MistyErrorEvent, MistyErrorKind, MistyErrorSource},
     signals::{MistySignal, SignalEmitter},
 };
 
 pub(crate) struct ScheduleManager {
     tasks: Arc<RwLock<Vec<ScheduledTask>>>,
+    delayed: Mutex<DelayedSchedules>,
+}
+
+/// Handlers waiting on timers of the async task runtime before being enqueued.
+#[derive(Default)]
+struct DelayedSchedules {
+    alloc: u64,
+    /// Host task ids of the timers, by their entry ids. `None` until the timer is spawned.
+    timers: HashMap<u64, Option<u64>>,
+    /// Entry ids of debounced handlers, by their tags.
+    debounced: HashMap<String, u64>,
+    /// Throttle windows by their tags, with the handler to run when the window ends.
+    throttled: HashMap<String, Option<ScheduledTask>>,
+}
+
+impl DelayedSchedules {
+    fn reserve_timer(&mut self) -> u64 {
+        self.alloc += 1;
+        self.timers.insert(self.alloc, None);
+        self.alloc
+    }
+}
+
+/// Runs under the lock when a timer fires, and returns what to run after releasing it.
+type OnTimerFire = Box<dyn FnOnce(&mut DelayedSchedules) -> Option<AfterTimerFire> + Send>;
+type AfterTimerFire = Box<dyn FnOnce(&Arc<MistyClientInner>) + Send>;
+
+fn push_after_fire(task: ScheduledTask) -> Option<AfterTimerFire> {
+    Some(Box::new(move |inner| {
+        inner.schedule_manager.push(&inner.signal_emitter, task);
+    }))
+}
+
+/// Ends the throttle window of `tag`. A trailing handler is enqueued and opens the next window.
+fn throttle_window_end(tag: String, duration: Duration) -> OnTimerFire {
+    Box::new(move |delayed| {
+        let Some(task) = delayed.throttled.get_mut(&tag).and_then(|t| t.take()) else {
+            delayed.throttled.remove(&tag);
+            return None;
+        };
+        let id = delayed.reserve_timer();
+        Some(Box::new(move |inner| {
+            let schedule_manager = &inner.schedule_manager;
+            schedule_manager.push(&inner.signal_emitter, task);
+            schedule_manager.spawn_timer(inner, id, duration, throttle_window_end(tag, duration));
+        }))
+    })
 }
 
+type ScheduledHandler = Box<dyn FnOnce(MistyClientHandle) -> Result<(), String> + Send + Sync>;
+
 pub(crate) struct ScheduledTask {
-    handler: Box<dyn FnOnce(MistyClientHandle) + Send + Sync>,
+    handler: ScheduledHandler,
+    /// Type name of the async task scheduling the handler.
+    task_type: Option<&'static str>,
+    error_update: bool,
+    /// Errors are returned to the scheduling task, so only panics are reported.
+    returns_error: bool,
+}
+
+#[derive(Debug)]
+pub enum MistyScheduleError<E> {
+    Failed(E),
+    /// The client was destroyed before the handler ran, or the handler panicked.
+    Cancelled,
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for MistyScheduleError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Failed(err) => write!(f, "{}", err),
+            Self::Cancelled => write!(f, "scheduled handler is cancelled"),
+        }
+    }
 }
 
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for MistyScheduleError<E> {}
+
 impl ScheduledTask {
     fn new<E>(
+        task_type: Option<&'static str>,
         handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
     ) -> Self
     where
@@ -29,13 +108,69 @@ impl ScheduledTask {
                 let err = handler(handle);
                 if let Err(err) = err {
                     tracing::error!("schedule fail, error: {}", err);
+                    return Err(err.to_string());
                 }
+                Ok(())
             }),
+            task_type,
+            error_update: false,
+            returns_error: false,
         }
     }
 
+    /// The result of the handler is sent to `tx`. Failed handlers still roll back their
+    /// state updates.
+    fn with_result<T, E>(
+        task_type: Option<&'static str>,
+        tx: oneshot::Sender<Result<T, E>>,
+        handler: impl FnOnce(MistyClientHandle) -> Result<T, E> + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        Self {
+            handler: Box::new(|handle| {
+                let res = handler(handle);
+                let failed = res.is_err();
+                let _ = tx.send(res);
+                if failed {
+                    return Err(Default::default());
+                }
+                Ok(())
+            }),
+            task_type,
+            error_update: false,
+            returns_error: true,
+        }
+    }
+
+    /// Runs the task in its own mut span, so a failed or panicking task only rolls back
+    /// its own state updates.
     fn run(self, handle: MistyClientHandle) {
-        (self.handler)(handle);
+        handle.inner.enter_mut_span();
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| (self.handler)(handle)));
+        let error = match res {
+            Ok(Ok(())) => None,
+            Ok(Err(err)) => Some(MistyErrorKind::Error(err)),
+            Err(payload) => {
+                tracing::error!("schedule panic");
+                Some(MistyErrorKind::Panic(panic_message(payload.as_ref())))
+            }
+        };
+        handle.inner.leave_mut_span(error.is_some());
+
+        if let Some(kind) = error {
+            if self.returns_error && matches!(kind, MistyErrorKind::Error(_)) {
+                return;
+            }
+            let event = MistyErrorEvent {
+                source: MistyErrorSource::Schedule,
+                task_type: self.task_type,
+                kind,
+            };
+            report_error(handle.inner, event, !self.error_update);
+        }
     }
 }
 
@@ -43,23 +178,208 @@ impl ScheduleManager {
     pub fn new() -> Self {
         Self {
             tasks: Default::default(),
+            delayed: Default::default(),
         }
     }
 
     pub fn enqueue<E>(
         &self,
         signal_emitter: &SignalEmitter,
+        task_type: Option<&'static str>,
         handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
     ) where
         E: std::fmt::Display,
     {
+        self.push(signal_emitter, ScheduledTask::new(task_type, handler));
+    }
+
+    /// Receives the result of the handler, or is cancelled if the handler never returns.
+    pub fn enqueue_with_result<T, E>(
+        &self,
+        signal_emitter: &SignalEmitter,
+        task_type: Option<&'static str>,
+        handler: impl FnOnce(MistyClientHandle) -> Result<T, E> + Send + Sync + 'static,
+    ) -> oneshot::Receiver<Result<T, E>>
+    where
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.push(
+            signal_emitter,
+            ScheduledTask::with_result(task_type, tx, handler),
+        );
+        rx
+    }
+
+    /// Schedules the error updater of the client, whose own errors are only reported.
+    pub fn enqueue_error_update(
+        &self,
+        signal_emitter: &SignalEmitter,
+        handler: impl FnOnce(MistyClientHandle) + Send + Sync + 'static,
+    ) {
+        let mut task = ScheduledTask::new(None, move |handle| {
+            handler(handle);
+            Ok::<(), Infallible>(())
+        });
+        task.error_update = true;
+        self.push(signal_emitter, task);
+    }
+
+    fn push(&self, signal_emitter: &SignalEmitter, task: ScheduledTask) {
         {
             let mut tasks = self.tasks.write().unwrap();
-            tasks.push(ScheduledTask::new(handler));
+            tasks.push(task);
         }
         signal_emitter.emit(MistySignal::Schedule);
     }
 
+    /// Enqueues the handler after `duration`.
+    pub fn enqueue_after<E>(
+        &self,
+        inner: &Arc<MistyClientInner>,
+        task_type: Option<&'static str>,
+        duration: Duration,
+        handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
+    ) where
+        E: std::fmt::Display,
+    {
+        let task = ScheduledTask::new(task_type, handler);
+        let id = self.delayed.lock().unwrap().reserve_timer();
+        self.spawn_timer(
+            inner,
+            id,
+            duration,
+            Box::new(move |_| push_after_fire(task)),
+        );
+    }
+
+    /// Enqueues the handler after `duration`, unless another handler is debounced with the same
+    /// tag before then, which replaces it and waits again.
+    pub fn enqueue_debounced<E>(
+        &self,
+        inner: &Arc<MistyClientInner>,
+        task_type: Option<&'static str>,
+        tag: String,
+        duration: Duration,
+        handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
+    ) where
+        E: std::fmt::Display,
+    {
+        let task = ScheduledTask::new(task_type, handler);
+        let (id, replaced) = {
+            let mut delayed = self.delayed.lock().unwrap();
+            let replaced = delayed
+                .debounced
+                .remove(&tag)
+                .and_then(|id| delayed.timers.remove(&id))
+                .flatten();
+            let id = delayed.reserve_timer();
+            delayed.debounced.insert(tag.clone(), id);
+            (id, replaced)
+        };
+        // aborted without the lock, since the aborted timer may be dropped at once
+        if let Some(host_task_id) = replaced {
+            inner.async_task_runtime.try_abort(host_task_id);
+        }
+
+        let on_fire: OnTimerFire = Box::new(move |delayed| {
+            // a later handler of the tag may already be waiting on its own timer
+            if delayed.debounced.get(&tag) == Some(&id) {
+                delayed.debounced.remove(&tag);
+            }
+            push_after_fire(task)
+        });
+        self.spawn_timer(inner, id, duration, on_fire);
+    }
+
+    /// Enqueues the handler at once if no handler is throttled with the same tag in the last
+    /// `duration`. Otherwise it is enqueued when that window ends, unless replaced by a later
+    /// handler of the tag.
+    pub fn enqueue_throttled<E>(
+        &self,
+        inner: &Arc<MistyClientInner>,
+        task_type: Option<&'static str>,
+        tag: String,
+        duration: Duration,
+        handler: impl FnOnce(MistyClientHandle) -> Result<(), E> + Send + Sync + 'static,
+    ) where
+        E: std::fmt::Display,
+    {
+        let task = ScheduledTask::new(task_type, handler);
+        let id = {
+            let mut delayed = self.delayed.lock().unwrap();
+            if let Some(trailing) = delayed.throttled.get_mut(&tag) {
+                *trailing = Some(task);
+                return;
+            }
+            delayed.throttled.insert(tag.clone(), None);
+            delayed.reserve_timer()
+        };
+        self.push(&inner.signal_emitter, task);
+        self.spawn_timer(inner, id, duration, throttle_window_end(tag, duration));
+    }
+
+    /// Spawns the timer of the entry `id`, which calls `on_fire` under the lock unless the entry
+    /// is removed before then. Must be called without holding the lock, since the runtime may
+    /// poll the timer at once.
+    fn spawn_timer(
+        &self,
+        inner: &Arc<MistyClientInner>,
+        id: u64,
+        duration: Duration,
+        on_fire: OnTimerFire,
+    ) {
+        let rt = inner.async_task_runtime.as_ref();
+        let sleep = rt.sleep(duration);
+        let weak = Arc::downgrade(inner);
+
+        let host_task_id = rt.spawn(Box::pin(async move {
+            sleep.await;
+            let Some(inner) = weak.upgrade() else {
+                return;
+            };
+            let after_fire = {
+                let mut delayed = inner.schedule_manager.delayed.lock().unwrap();
+                if delayed.timers.remove(&id).is_none() || inner.is_destroyed() {
+                    return;
+                }
+                on_fire(&mut delayed)
+            };
+            if let Some(after_fire) = after_fire {
+                after_fire(&inner);
+            }
+        }));
+
+        let removed = {
+            let mut delayed = self.delayed.lock().unwrap();
+            match delayed.timers.get_mut(&id) {
+                Some(timer) => {
+                    *timer = Some(host_task_id);
+                    false
+                }
+                None => true,
+            }
+        };
+        // the entry is removed if the timer is cancelled or fires before this
+        if removed {
+            rt.try_abort(host_task_id);
+        }
+    }
+
+    /// Aborts the timers of all delayed handlers.
+    pub fn cancel_delayed(&self, inner: &MistyClientInner) {
+        let timers = {
+            let mut delayed = self.delayed.lock().unwrap();
+            delayed.debounced.clear();
+            delayed.throttled.clear();
+            std::mem::take(&mut delayed.timers)
+        };
+        for host_task_id in timers.into_values().flatten() {
+            inner.async_task_runtime.try_abort(host_task_id);
+        }
+    }
+
     pub fn take_all_tasks(&self) -> Vec<ScheduledTask> {
         let mut current_tasks = vec![];
         {
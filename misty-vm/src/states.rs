@@ -4,13 +4,16 @@ use std::{
     collections::{HashMap, HashSet},
     fmt::Debug,
     marker::PhantomData,
-    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak},
+    sync::{Arc, Condvar, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak},
 };
 
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use thread_local::ThreadLocal;
 
 use crate::{
     client::{AsMistyClientHandle, AsReadonlyMistyClientHandle, MistyClientInner},
+    controllers::MistyControllerContext,
+    undo::{MistyUndoState, UndoChange, UndoHistory},
     utils::extend_lifetime,
 };
 
@@ -23,13 +26,30 @@ impl MistyStateId {
     }
 }
 
-pub trait MistyStateTrait: Any + Default + Send + Sync + 'static {
+pub trait MistyStateTrait: Any + Default + Clone + Send + Sync + 'static {
     fn id() -> MistyStateId {
         MistyStateId::new(std::any::TypeId::of::<Self>())
     }
 
+    /// Set by `#[misty(persist)]`. Persisted states are included in snapshots.
+    fn persistence() -> Option<MistyStatePersistence> {
+        None
+    }
+
+    /// Set by `#[misty(undoable)]`. Updates of undoable states are recorded for undo/redo.
+    fn undoable() -> bool {
+        false
+    }
+
+    /// Set by `#[misty(computed)]`. See [`MistyComputedTrait`].
+    fn computed() -> Option<MistyComputedState> {
+        None
+    }
+
     fn map<'a, R>(cx: impl AsReadonlyMistyClientHandle<'a>, func: impl FnOnce(&Self) -> R) -> R {
-        let states = cx.readonly_handle().inner.state_manager.states();
+        let state_manager = &cx.readonly_handle().inner.state_manager;
+        state_manager.refresh_computed(Self::id());
+        let states = state_manager.states();
         let binding = states.get::<Self>();
         let state = binding.downcast();
         let ret = func(state.get());
@@ -41,6 +61,10 @@ pub trait MistyStateTrait: Any + Default + Send + Sync + 'static {
         func: impl FnOnce(&mut Self) -> R,
     ) -> R {
         let client_ref = cx.handle();
+        if Self::computed().is_some() {
+            let typ_name = std::any::type_name::<Self>();
+            panic!("cannot update computed state {}", typ_name);
+        }
         let can_update = client_ref.inner.state_manager.can_update();
         if !can_update {
             let typ_name = std::any::type_name::<Self>();
@@ -48,6 +72,10 @@ pub trait MistyStateTrait: Any + Default + Send + Sync + 'static {
             panic!("[{:?}] cannot update state {} in this stage", pid, typ_name);
         }
 
+        client_ref
+            .inner
+            .state_manager
+            .backup(StateOps::of::<Self>());
         client_ref.inner.state_manager.add_update_state::<Self>();
         let states = cx.readonly_handle().inner.state_manager.states();
         let binding = states.get::<Self>();
@@ -59,6 +87,15 @@ pub trait MistyStateTrait: Any + Default + Send + Sync + 'static {
     }
 }
 
+/// A state derived from other states. Mark it with `#[misty(computed)]`. The value is cached
+/// and recomputed on the next read after any of `Deps` is updated, and it can be read with
+/// `map` or used in view models like any other state. Updating it directly panics.
+pub trait MistyComputedTrait: MistyStateTrait {
+    type Deps<'a>: RefMistyStates;
+
+    fn compute(deps: Self::Deps<'_>) -> Self;
+}
+
 pub trait RefMistyStates {
     fn state_ids() -> Vec<MistyStateId>;
     fn extract_refs(cx: &MistyStateManager, handler: impl FnOnce(Self))
@@ -81,6 +118,7 @@ macro_rules! impl_ref_states_tuple {
             where
                 Self: Sized,
             {
+                $(cx.refresh_computed($t::id());)+
                 let states = cx.states();
                 let t = (
                     $(states.get::<$t>()),+,
@@ -119,6 +157,62 @@ impl_ref_states_tuple!(
     0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5, T6, 6, T7, 7, T8, 8, T9, 9, T10, 10, T11, 11, T12
 );
 
+/// A state, or a tuple of states read together by `MistyAsyncTaskContext::read`.
+pub trait MistyReadStates {
+    type Refs<'a>;
+
+    /// Reads copies of the states taken while no controller or scheduled handler is updating
+    /// states, so they are consistent with each other. Updates are not blocked by `func`.
+    fn read<R>(state_manager: &MistyStateManager, func: impl FnOnce(Self::Refs<'_>) -> R) -> R;
+}
+
+impl<T: MistyStateTrait> MistyReadStates for T {
+    type Refs<'a> = &'a T;
+
+    fn read<R>(state_manager: &MistyStateManager, func: impl FnOnce(Self::Refs<'_>) -> R) -> R {
+        let state = state_manager.snapshot_consistent(|| {
+            state_manager.refresh_computed(T::id());
+            let binding = state_manager.states().get::<T>();
+            let value = binding.downcast::<T>().get().clone();
+            value
+        });
+        func(&state)
+    }
+}
+
+macro_rules! impl_read_states_tuple {
+    ($($n:tt, $t:ident),+) => {
+        impl<$($t),+> MistyReadStates for ($($t),+)
+        where
+            $($t: MistyStateTrait),+
+        {
+            type Refs<'a> = ($(&'a $t),+);
+
+            fn read<R>(
+                state_manager: &MistyStateManager,
+                func: impl FnOnce(Self::Refs<'_>) -> R,
+            ) -> R {
+                let states = state_manager.snapshot_consistent(|| {
+                    $(state_manager.refresh_computed($t::id());)+
+                    let states = state_manager.states();
+                    let bindings = ($(states.get::<$t>()),+);
+                    let guards = ($(bindings.$n.downcast::<$t>()),+);
+                    ($(guards.$n.get().clone()),+)
+                });
+                func(($(&states.$n),+))
+            }
+        }
+    };
+}
+
+impl_read_states_tuple!(0, T1, 1, T2);
+impl_read_states_tuple!(0, T1, 1, T2, 2, T3);
+impl_read_states_tuple!(0, T1, 1, T2, 2, T3, 3, T4);
+impl_read_states_tuple!(0, T1, 1, T2, 2, T3, 3, T4, 4, T5);
+impl_read_states_tuple!(0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5, T6);
+impl_read_states_tuple!(0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5, T6, 6, T7);
+impl_read_states_tuple!(0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5, T6, 6, T7, 7, T8);
+
 #[derive(Debug, Clone)]
 struct BoxedState {
     inner: Arc<RwLock<dyn Any + Send + Sync>>,
@@ -137,13 +231,87 @@ struct StateWrite<'a, T> {
 #[derive(Debug)]
 pub struct States {
     inner: HashMap<MistyStateId, BoxedState>,
+    persisted: Vec<MistyStatePersistence>,
+    computed: HashMap<MistyStateId, MistyComputedState>,
+}
+
+/// How a state marked with `#[misty(computed)]` is computed from its dependencies.
+#[derive(Debug, Clone, Copy)]
+pub struct MistyComputedState {
+    deps: fn() -> Vec<MistyStateId>,
+    compute: fn(&MistyStateManager) -> Box<dyn Any + Send + Sync>,
+    ops: StateOps,
+}
+
+/// How a state marked with `#[misty(persist)]` is written to and read from a snapshot.
+#[derive(Debug, Clone, Copy)]
+pub struct MistyStatePersistence {
+    key: &'static str,
+    encode: fn(&States) -> Result<serde_json::Value, serde_json::Error>,
+    decode: fn(serde_json::Value) -> Result<Box<dyn Any + Send + Sync>, serde_json::Error>,
+    ops: StateOps,
+}
+
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Deserialize)]
+struct SnapshotHeader {
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    states: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug)]
+pub enum MistyStateSnapshotError {
+    UnsupportedVersion(u32),
+    Codec(serde_json::Error),
+}
+
+/// Type-erased operations on a state.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StateOps {
+    id: MistyStateId,
+    undoable: bool,
+    capture: fn(&States) -> Box<dyn Any + Send + Sync>,
+    restore: fn(&States, Box<dyn Any + Send + Sync>),
+    clone: fn(&(dyn Any + Send + Sync)) -> Box<dyn Any + Send + Sync>,
+}
+
+/// Value of a state before it was first updated in a mut span.
+#[derive(Debug)]
+struct StateBackup {
+    value: Box<dyn Any + Send + Sync>,
+    ops: StateOps,
+    was_updated: bool,
+    undoable: bool,
+}
+
+/// One mut span, i.e. one controller call or one scheduled task.
+#[derive(Debug, Default)]
+struct StateFrame {
+    backups: HashMap<MistyStateId, StateBackup>,
+    /// Computed states that became updated in the span, because their dependencies did.
+    updated_computed: HashSet<MistyStateId>,
+    partial_commit: bool,
+    skip_history: bool,
 }
 
 #[derive(Debug)]
 pub struct MistyStateManager {
     states: States,
     updated_state: ThreadLocal<RefCell<HashSet<MistyStateId>>>,
-    depth: ThreadLocal<RefCell<u32>>,
+    frames: ThreadLocal<RefCell<Vec<StateFrame>>>,
+    undo_history: Mutex<UndoHistory>,
+    /// Computed states by the states they depend on.
+    dependents: HashMap<MistyStateId, Vec<MistyStateId>>,
+    dirty_computed: Mutex<HashSet<MistyStateId>>,
+    /// Threads in mut spans. Consistent reads wait for none, and hold the lock while reading.
+    spans: Mutex<usize>,
+    spans_left: Condvar,
 }
 
 impl<'a, T: 'static> StateRead<'a, T> {
@@ -182,15 +350,96 @@ impl BoxedState {
     }
 }
 
+impl MistyStatePersistence {
+    pub fn new<T>(key: &'static str) -> Self
+    where
+        T: MistyStateTrait + Serialize + DeserializeOwned,
+    {
+        Self {
+            key,
+            encode: encode_persisted_state::<T>,
+            decode: decode_persisted_state::<T>,
+            ops: StateOps::of::<T>(),
+        }
+    }
+}
+
+fn encode_persisted_state<T>(states: &States) -> Result<serde_json::Value, serde_json::Error>
+where
+    T: MistyStateTrait + Serialize,
+{
+    let binding = states.get::<T>();
+    let state = binding.downcast::<T>();
+    serde_json::to_value(state.get())
+}
+
+fn decode_persisted_state<T>(
+    value: serde_json::Value,
+) -> Result<Box<dyn Any + Send + Sync>, serde_json::Error>
+where
+    T: MistyStateTrait + DeserializeOwned,
+{
+    let state: T = serde_json::from_value(value)?;
+    Ok(Box::new(state))
+}
+
+impl MistyComputedState {
+    pub fn new<T: MistyComputedTrait>() -> Self {
+        Self {
+            deps: <T::Deps<'static> as RefMistyStates>::state_ids,
+            compute: compute_state::<T>,
+            ops: StateOps::of::<T>(),
+        }
+    }
+}
+
+fn compute_state<T: MistyComputedTrait>(manager: &MistyStateManager) -> Box<dyn Any + Send + Sync> {
+    let mut value: Option<T> = None;
+    <T::Deps<'_> as RefMistyStates>::extract_refs(manager, |deps| {
+        value = Some(T::compute(deps));
+    });
+    Box::new(value.unwrap())
+}
+
+impl std::fmt::Display for MistyStateSnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {}", version)
+            }
+            Self::Codec(err) => write!(f, "snapshot codec error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for MistyStateSnapshotError {}
+
+impl From<serde_json::Error> for MistyStateSnapshotError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Codec(err)
+    }
+}
+
 impl States {
     pub fn new() -> Self {
         Self {
             inner: Default::default(),
+            persisted: Default::default(),
+            computed: Default::default(),
         }
     }
 
     pub fn register<T: MistyStateTrait>(&mut self) {
         self.inner.insert(T::id(), BoxedState::new::<T>());
+        if let Some(persistence) = T::persistence() {
+            if self.persisted.iter().any(|p| p.key == persistence.key) {
+                panic!("persist key {} is registered twice", persistence.key);
+            }
+            self.persisted.push(persistence);
+        }
+        if let Some(computed) = T::computed() {
+            self.computed.insert(T::id(), computed);
+        }
     }
 
     fn get<T: MistyStateTrait>(&self) -> BoxedState {
@@ -199,52 +448,356 @@ impl States {
     }
 }
 
+impl StateOps {
+    pub(crate) fn of<T: MistyStateTrait>() -> Self {
+        Self {
+            id: T::id(),
+            undoable: T::undoable(),
+            capture: capture_state::<T>,
+            restore: restore_state::<T>,
+            clone: clone_state::<T>,
+        }
+    }
+
+    pub(crate) fn clone_value(
+        &self,
+        value: &(dyn Any + Send + Sync),
+    ) -> Box<dyn Any + Send + Sync> {
+        (self.clone)(value)
+    }
+
+    /// Replaces the value of the state. In a mut span, it is recorded like an update.
+    pub(crate) fn replace(&self, manager: &MistyStateManager, value: Box<dyn Any + Send + Sync>) {
+        if manager.can_update() {
+            manager.backup(*self);
+            manager.mark_updated(self.id);
+        } else {
+            manager.invalidate_dependents(self.id);
+        }
+        (self.restore)(&manager.states, value);
+    }
+}
+
+fn capture_state<T: MistyStateTrait>(states: &States) -> Box<dyn Any + Send + Sync> {
+    let binding = states.get::<T>();
+    let value = binding.downcast::<T>().get().clone();
+    Box::new(value)
+}
+
+fn clone_state<T: MistyStateTrait>(value: &(dyn Any + Send + Sync)) -> Box<dyn Any + Send + Sync> {
+    let value: &T = value.downcast_ref().unwrap();
+    Box::new(value.clone())
+}
+
+fn restore_state<T: MistyStateTrait>(states: &States, value: Box<dyn Any + Send + Sync>) {
+    let value: Box<T> = value.downcast().unwrap();
+    let binding = states.get::<T>();
+    {
+        // the lock is poisoned if the update panicked, but the value is replaced anyway
+        let mut state = binding
+            .inner
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        *state.downcast_mut::<T>().unwrap() = *value;
+    }
+    binding.inner.clear_poison();
+}
+
 impl MistyStateManager {
-    pub fn new(states: States) -> Self {
+    pub fn new(mut states: States) -> Self {
+        states.register::<MistyUndoState>();
+        let mut dependents: HashMap<MistyStateId, Vec<MistyStateId>> = Default::default();
+        for (id, computed) in states.computed.iter() {
+            for dep in (computed.deps)().into_iter() {
+                dependents.entry(dep).or_default().push(*id);
+            }
+        }
+        let dirty_computed = states.computed.keys().copied().collect();
         MistyStateManager {
             states,
             updated_state: Default::default(),
-            depth: Default::default(),
+            frames: Default::default(),
+            undo_history: Default::default(),
+            dependents,
+            dirty_computed: Mutex::new(dirty_computed),
+            spans: Default::default(),
+            spans_left: Default::default(),
         }
     }
 
+    /// Sets how many controller calls can be undone. Defaults to 100.
+    pub fn with_undo_limit(self, limit: usize) -> Self {
+        self.undo_history.lock().unwrap().set_limit(limit);
+        self
+    }
+
     pub(crate) fn states(&self) -> &States {
         &self.states
     }
 
+    /// Encodes all persisted states into a versioned blob.
+    pub fn snapshot(&self) -> Result<Vec<u8>, MistyStateSnapshotError> {
+        let mut snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            states: Default::default(),
+        };
+        for persistence in self.states.persisted.iter() {
+            let value = (persistence.encode)(&self.states)?;
+            snapshot.states.insert(persistence.key.to_string(), value);
+        }
+        Ok(serde_json::to_vec(&snapshot)?)
+    }
+
+    /// Restores persisted states from a blob made by [`MistyStateManager::snapshot`].
+    /// Nothing is restored if any state fails to decode. States missing in the blob are kept.
+    pub fn restore(&self, buf: &[u8]) -> Result<(), MistyStateSnapshotError> {
+        let header: SnapshotHeader = serde_json::from_slice(buf)?;
+        if header.version != SNAPSHOT_VERSION {
+            return Err(MistyStateSnapshotError::UnsupportedVersion(header.version));
+        }
+        let mut snapshot: Snapshot = serde_json::from_slice(buf)?;
+
+        let mut decoded = vec![];
+        for persistence in self.states.persisted.iter() {
+            if let Some(value) = snapshot.states.remove(persistence.key) {
+                decoded.push((persistence, (persistence.decode)(value)?));
+            }
+        }
+        self.set_skip_history();
+        for (persistence, value) in decoded.into_iter() {
+            persistence.ops.replace(self, value);
+        }
+        Ok(())
+    }
+
     pub(crate) fn enter_mut_span(&self) {
-        let mut mut_update = self.depth.get_or_default().borrow_mut();
-        *mut_update += 1;
+        let mut frames = self.frames.get_or_default().borrow_mut();
+        if frames.is_empty() {
+            *self.spans.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+        }
+        frames.push(Default::default());
     }
-    pub(crate) fn leave_mut_span(&self) -> bool {
-        let mut mut_update = self.depth.get_or_default().borrow_mut();
-        if *mut_update <= 0 {
-            panic!(
-                "[Internal Error] MistyClientThisNotifyState depth is {}",
-                *mut_update
-            );
+    /// Leaves the current mut span. If `failed`, states updated in the span are restored,
+    /// unless the span opted in to partial commit. Returns whether the outermost span is left.
+    pub(crate) fn leave_mut_span(&self, failed: bool) -> bool {
+        let outermost = self.leave_frame(failed);
+        if outermost {
+            *self.spans.lock().unwrap_or_else(PoisonError::into_inner) -= 1;
+            self.spans_left.notify_all();
+        }
+        outermost
+    }
+    fn leave_frame(&self, failed: bool) -> bool {
+        let mut frames = self.frames.get_or_default().borrow_mut();
+        let frame = frames
+            .pop()
+            .expect("[Internal Error] leave mut span without entering it");
+
+        if failed && !frame.partial_commit {
+            let mut updated_state = self.updated_state.get_or_default().borrow_mut();
+            for (state_id, backup) in frame.backups.into_iter() {
+                (backup.ops.restore)(&self.states, backup.value);
+                if !backup.was_updated {
+                    updated_state.remove(&state_id);
+                }
+                // computed in the span from the discarded values, so they are computed again
+                self.invalidate_dependents(state_id);
+            }
+            for state_id in frame.updated_computed.iter() {
+                updated_state.remove(state_id);
+            }
+        } else if let Some(parent) = frames.last_mut() {
+            for (state_id, backup) in frame.backups.into_iter() {
+                parent.backups.entry(state_id).or_insert(backup);
+            }
+            parent.updated_computed.extend(frame.updated_computed);
+        } else {
+            drop(frames);
+            self.record_undo(frame.backups);
+            return true;
         }
-        *mut_update -= 1;
 
-        let is_depth_zero = *mut_update == 0;
-        return is_depth_zero;
+        return frames.is_empty();
+    }
+    pub(crate) fn set_partial_commit(&self) {
+        let mut frames = self.frames.get_or_default().borrow_mut();
+        if let Some(frame) = frames.last_mut() {
+            frame.partial_commit = true;
+        }
+    }
+    pub(crate) fn is_partial_commit(&self) -> bool {
+        let frames = self.frames.get_or_default().borrow();
+        frames.last().is_some_and(|frame| frame.partial_commit)
+    }
+    pub(crate) fn set_skip_history(&self) {
+        let mut frames = self.frames.get_or_default().borrow_mut();
+        if let Some(frame) = frames.last_mut() {
+            frame.skip_history = true;
+        }
     }
     pub(crate) fn clear_updated_states(&self) {
         self.updated_state.get_or_default().borrow_mut().clear();
     }
-    pub(crate) fn reset(&self) {
-        self.updated_state.get_or_default().borrow_mut().clear();
-        *self.depth.get_or_default().borrow_mut() = 0;
+    pub(crate) fn has_updated_states(&self) -> bool {
+        !self.updated_state.get_or_default().borrow().is_empty()
     }
     pub(crate) fn can_update(&self) -> bool {
-        return *self.depth.get_or_default().borrow() > 0;
+        return !self.frames.get_or_default().borrow().is_empty();
     }
 
-    pub(crate) fn add_update_state<S: MistyStateTrait>(&self) {
-        self.updated_state
+    pub(crate) fn backup(&self, ops: StateOps) {
+        let mut frames = self.frames.get_or_default().borrow_mut();
+        let frame = frames.last_mut().unwrap();
+        if frame.backups.contains_key(&ops.id) {
+            return;
+        }
+
+        let was_updated = self
+            .updated_state
             .get_or_default()
-            .borrow_mut()
-            .insert(S::id());
+            .borrow()
+            .contains(&ops.id);
+        frame.backups.insert(
+            ops.id,
+            StateBackup {
+                value: (ops.capture)(&self.states),
+                ops,
+                was_updated,
+                undoable: ops.undoable && !frame.skip_history,
+            },
+        );
+    }
+
+    fn record_undo(&self, backups: HashMap<MistyStateId, StateBackup>) {
+        let changes: Vec<UndoChange> = backups
+            .into_values()
+            .filter(|backup| backup.undoable)
+            .map(|backup| UndoChange {
+                after: (backup.ops.capture)(&self.states),
+                before: backup.value,
+                ops: backup.ops,
+            })
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
+
+        self.undo_history.lock().unwrap().push(changes);
+        self.sync_undo_state();
+    }
+
+    /// Replays the last undoable controller call backwards. Must be called in a mut span.
+    pub(crate) fn undo(&self) {
+        self.set_skip_history();
+        let changes = self.undo_history.lock().unwrap().pop_undo();
+        if let Some(changes) = changes {
+            for change in changes.iter() {
+                change
+                    .ops
+                    .replace(self, change.ops.clone_value(change.before.as_ref()));
+            }
+            self.undo_history.lock().unwrap().push_redo(changes);
+            self.sync_undo_state();
+        }
+    }
+
+    /// Replays the last undone controller call. Must be called in a mut span.
+    pub(crate) fn redo(&self) {
+        self.set_skip_history();
+        let changes = self.undo_history.lock().unwrap().pop_redo();
+        if let Some(changes) = changes {
+            for change in changes.iter() {
+                change
+                    .ops
+                    .replace(self, change.ops.clone_value(change.after.as_ref()));
+            }
+            self.undo_history.lock().unwrap().push_undo(changes);
+            self.sync_undo_state();
+        }
+    }
+
+    fn sync_undo_state(&self) {
+        let state = {
+            let history = self.undo_history.lock().unwrap();
+            MistyUndoState {
+                can_undo: history.can_undo(),
+                can_redo: history.can_redo(),
+            }
+        };
+        let changed = {
+            let binding = self.states.get::<MistyUndoState>();
+            let current = binding.downcast::<MistyUndoState>();
+            *current.get() != state
+        };
+        if changed {
+            self.add_update_state::<MistyUndoState>();
+            restore_state::<MistyUndoState>(&self.states, Box::new(state));
+        }
+    }
+
+    pub(crate) fn add_update_state<S: MistyStateTrait>(&self) {
+        self.mark_updated(S::id());
+    }
+
+    /// Marks the state and the computed states depending on it as updated.
+    fn mark_updated(&self, state_id: MistyStateId) {
+        let mut updated_state = self.updated_state.get_or_default().borrow_mut();
+        updated_state.insert(state_id);
+        let mut frames = self.frames.get_or_default().borrow_mut();
+        for computed_id in self.invalidate_dependents(state_id) {
+            if updated_state.insert(computed_id) {
+                if let Some(frame) = frames.last_mut() {
+                    frame.updated_computed.insert(computed_id);
+                }
+            }
+        }
+    }
+
+    /// Marks computed states depending on the state, directly or not, as dirty, and returns
+    /// them.
+    fn invalidate_dependents(&self, state_id: MistyStateId) -> HashSet<MistyStateId> {
+        let mut computed: HashSet<MistyStateId> = Default::default();
+        if !self.dependents.contains_key(&state_id) {
+            return computed;
+        }
+        let mut dirty_computed = self.dirty_computed.lock().unwrap();
+        let mut stack = vec![state_id];
+        while let Some(state_id) = stack.pop() {
+            for computed_id in self.dependents.get(&state_id).into_iter().flatten() {
+                if computed.insert(*computed_id) {
+                    dirty_computed.insert(*computed_id);
+                    stack.push(*computed_id);
+                }
+            }
+        }
+        computed
+    }
+
+    /// Runs `snapshot` while no other thread is in a mut span, holding off new spans until it
+    /// returns. States updated by this thread in its own span are read as they are.
+    pub(crate) fn snapshot_consistent<R>(&self, snapshot: impl FnOnce() -> R) -> R {
+        if self.can_update() {
+            return snapshot();
+        }
+        // a panicking snapshot poisons the lock, but leaves the count intact
+        let spans = self.spans.lock().unwrap_or_else(PoisonError::into_inner);
+        let _spans = self
+            .spans_left
+            .wait_while(spans, |spans| *spans > 0)
+            .unwrap_or_else(PoisonError::into_inner);
+        snapshot()
+    }
+
+    /// Recomputes the state if it is a dirty computed state.
+    pub(crate) fn refresh_computed(&self, state_id: MistyStateId) {
+        let Some(computed) = self.states.computed.get(&state_id) else {
+            return;
+        };
+        if !self.dirty_computed.lock().unwrap().remove(&state_id) {
+            return;
+        }
+        let value = (computed.compute)(self);
+        (computed.ops.restore)(&self.states, value);
     }
 
     pub(crate) fn contains_updated_state(&self, state_ids: &Vec<MistyStateId>) -> bool {
@@ -255,13 +808,29 @@ impl MistyStateManager {
     }
 }
 
+pub(crate) fn controller_restore_states(
+    ctx: MistyControllerContext,
+    buf: &[u8],
+) -> Result<(), MistyStateSnapshotError> {
+    ctx.handle().inner.state_manager.restore(buf)
+}
+
 pub(crate) struct GuardCleanupStatesForPanic {
     inner: Weak<MistyClientInner>,
+    left: bool,
     mark: bool,
 }
 impl GuardCleanupStatesForPanic {
     pub fn new(inner: Weak<MistyClientInner>) -> GuardCleanupStatesForPanic {
-        GuardCleanupStatesForPanic { inner, mark: false }
+        GuardCleanupStatesForPanic {
+            inner,
+            left: false,
+            mark: false,
+        }
+    }
+    pub fn leave_mut_span(&mut self, inner: &MistyClientInner, failed: bool) -> bool {
+        self.left = true;
+        inner.leave_mut_span(failed)
     }
     pub fn mark(&mut self) {
         self.mark = true;
@@ -272,8 +841,11 @@ impl Drop for GuardCleanupStatesForPanic {
         if self.mark {
             return;
         }
+        // states kept by a partial commit stay updated, and the host is asked to flush them
         if let Some(inner) = self.inner.upgrade() {
-            inner.state_manager.reset();
+            if !self.left {
+                inner.leave_mut_span(true);
+            }
         }
     }
 }
@@ -0,0 +1,74 @@
+use std::{
+    pin::Pin,
+    sync::Weak,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{future::BoxFuture, FutureExt, Stream, StreamExt};
+
+use crate::client::MistyClientInner;
+
+/// Ticks every period on the async task runtime, created by `MistyAsyncTaskContext::interval`.
+/// The first tick completes at once. A tick late by a whole period or more delays the next
+/// ones, instead of firing the missed ticks together.
+pub struct MistyInterval {
+    inner: Weak<MistyClientInner>,
+    period: Duration,
+    /// Deadline of the next tick, on the clock of the runtime.
+    next: Option<Duration>,
+    sleep: Option<BoxFuture<'static, ()>>,
+}
+
+impl MistyInterval {
+    pub(crate) fn new(inner: Weak<MistyClientInner>, period: Duration) -> Self {
+        // every poll would tick at once
+        assert!(!period.is_zero(), "interval period must be non-zero");
+        Self {
+            inner,
+            period,
+            next: None,
+            sleep: None,
+        }
+    }
+
+    /// Completes at the next tick. Never completes if the client is released.
+    pub async fn tick(&mut self) {
+        if self.next().await.is_none() {
+            futures::future::pending::<()>().await;
+        }
+    }
+}
+
+impl Stream for MistyInterval {
+    type Item = ();
+
+    /// Ends when the client is released.
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(sleep) = this.sleep.as_mut() {
+                if sleep.poll_unpin(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                this.sleep = None;
+            }
+
+            let Some(inner) = this.inner.upgrade() else {
+                return Poll::Ready(None);
+            };
+            let rt = inner.async_task_runtime.as_ref();
+            let now = rt.now();
+            let next = *this.next.get_or_insert(now);
+            if now >= next {
+                this.next = Some(if now - next >= this.period {
+                    now + this.period
+                } else {
+                    next + this.period
+                });
+                return Poll::Ready(Some(()));
+            }
+            this.sleep = Some(rt.sleep(next - now));
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::{any::Any, collections::VecDeque, convert::Infallible};
+
+use crate::{
+    controllers::MistyControllerContext,
+    states::{MistyStateTrait, StateOps},
+};
+
+const DEFAULT_UNDO_LIMIT: usize = 100;
+
+/// Undo/redo availability. It is registered in every state manager, so view models can
+/// depend on it like on any other state.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MistyUndoState {
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
+impl MistyStateTrait for MistyUndoState {}
+
+#[derive(Debug)]
+pub(crate) struct UndoChange {
+    pub before: Box<dyn Any + Send + Sync>,
+    pub after: Box<dyn Any + Send + Sync>,
+    pub ops: StateOps,
+}
+
+#[derive(Debug)]
+pub(crate) struct UndoHistory {
+    undo: VecDeque<Vec<UndoChange>>,
+    redo: Vec<Vec<UndoChange>>,
+    limit: usize,
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self {
+            undo: Default::default(),
+            redo: Default::default(),
+            limit: DEFAULT_UNDO_LIMIT,
+        }
+    }
+}
+
+impl UndoHistory {
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.truncate();
+    }
+
+    /// Records changes of a new controller call, which discards the redo history.
+    pub fn push(&mut self, changes: Vec<UndoChange>) {
+        self.redo.clear();
+        self.push_undo(changes);
+    }
+
+    pub fn push_undo(&mut self, changes: Vec<UndoChange>) {
+        self.undo.push_back(changes);
+        self.truncate();
+    }
+
+    pub fn push_redo(&mut self, changes: Vec<UndoChange>) {
+        self.redo.push(changes);
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Vec<UndoChange>> {
+        self.undo.pop_back()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Vec<UndoChange>> {
+        self.redo.pop()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    fn truncate(&mut self) {
+        while self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+}
+
+/// Restores undoable states to their values before the last recorded controller call.
+pub fn controller_undo(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
+    ctx.handle().inner.state_manager.undo();
+    Ok(())
+}
+
+/// Re-applies the last undone controller call.
+pub fn controller_redo(ctx: MistyControllerContext, _arg: ()) -> Result<(), Infallible> {
+    ctx.handle().inner.state_manager.redo();
+    Ok(())
+}
@@ -1,20 +1,123 @@
-use std::{any::Any, fmt::Debug};
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    hash::Hash,
+    sync::{Mutex, PoisonError},
+};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
     client::MistyClientInner,
+    resources::ResourceUpdateAction,
     states::{MistyStateManager, RefMistyStates},
 };
 
+/// A root view. Derive it with `#[derive(MistyView)]`, which generates a `{Name}Patch` struct
+/// with every field wrapped in `Option`. Fields marked `#[misty(view)]` are diffed as nested
+/// views, and `Vec` fields marked `#[misty(list(key = field))]` as keyed lists.
+pub trait MistyViewTrait: Any + Default + Clone + Send + Sync + 'static {
+    type Patch: Send + Sync + 'static;
+
+    /// Returns `None` if nothing changed.
+    fn diff(prev: &Self, next: &Self) -> Option<Self::Patch>;
+    fn apply_patch(&mut self, patch: Self::Patch);
+    /// Clones from `next` only the fields that changed in `patch`, which was diffed from
+    /// `self` to `next`.
+    fn copy_changed(&mut self, next: &Self, patch: &Self::Patch);
+}
+
+/// An operation of a keyed list diff. Operations are applied in order, and indices refer to
+/// the list with all previous operations applied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ListDiffOp<T> {
+    Insert { index: usize, item: T },
+    Remove { index: usize },
+    Move { from: usize, to: usize },
+    Update { index: usize, item: T },
+}
+
+/// Diffs two lists by the key of their items, which should be unique in each list.
+pub fn diff_keyed_list<T, K>(prev: &[T], next: &[T], key: impl Fn(&T) -> K) -> Vec<ListDiffOp<T>>
+where
+    T: Clone + PartialEq,
+    K: Eq + Hash,
+{
+    let next_keys: HashSet<K> = next.iter().map(&key).collect();
+    let prev_items: HashMap<K, &T> = prev.iter().map(|item| (key(item), item)).collect();
+    let mut ops: Vec<ListDiffOp<T>> = Default::default();
+
+    // removes from the back, so indices of the remaining items are not shifted
+    let mut current: Vec<K> = Default::default();
+    for (index, item) in prev.iter().enumerate().rev() {
+        let k = key(item);
+        if next_keys.contains(&k) {
+            current.push(k);
+        } else {
+            ops.push(ListDiffOp::Remove { index });
+        }
+    }
+    current.reverse();
+
+    for (index, item) in next.iter().enumerate() {
+        let k = key(item);
+        let from = current[index.min(current.len())..]
+            .iter()
+            .position(|c| *c == k)
+            .map(|pos| pos + index);
+        match (from, prev_items.get(&k)) {
+            (Some(from), Some(prev_item)) => {
+                if from != index {
+                    let k = current.remove(from);
+                    current.insert(index, k);
+                    ops.push(ListDiffOp::Move { from, to: index });
+                }
+                if *prev_item != item {
+                    ops.push(ListDiffOp::Update {
+                        index,
+                        item: item.clone(),
+                    });
+                }
+            }
+            _ => {
+                current.insert(index, k);
+                ops.push(ListDiffOp::Insert {
+                    index,
+                    item: item.clone(),
+                });
+            }
+        }
+    }
+    return ops;
+}
+
+pub fn apply_list_diff<T>(list: &mut Vec<T>, ops: Vec<ListDiffOp<T>>) {
+    for op in ops.into_iter() {
+        match op {
+            ListDiffOp::Insert { index, item } => list.insert(index, item),
+            ListDiffOp::Remove { index } => {
+                list.remove(index);
+            }
+            ListDiffOp::Move { from, to } => {
+                let item = list.remove(from);
+                list.insert(to, item);
+            }
+            ListDiffOp::Update { index, item } => list[index] = item,
+        }
+    }
+}
+
 pub struct BoxedView {
     inner: Box<dyn Any + Send + Sync>,
 }
 
 impl BoxedView {
-    pub fn new<R: Any + Default + Send + Sync + 'static>(v: R) -> Self {
+    pub fn new<R: Any + Send + Sync + 'static>(v: R) -> Self {
         Self { inner: Box::new(v) }
     }
 
-    pub fn cast<R: Any + Default + Send + Sync + 'static>(self) -> R {
+    pub fn cast<R: Any + Send + Sync + 'static>(self) -> R {
         let r: Box<R> = self.inner.downcast().unwrap();
         return *r;
     }
@@ -79,8 +182,23 @@ impl<R, S> BoxedMistyViewModel<R, S> {
     }
 }
 
+pub(crate) struct ViewUpdate<P> {
+    pub patch: Option<P>,
+    pub changed_resources: Vec<ResourceUpdateAction>,
+    pub seq: u64,
+}
+
+/// The view last sent to the host, which the next patch is diffed against, and the draft
+/// view models update in place.
+struct ViewCache<R> {
+    view: R,
+    draft: R,
+    seq: u64,
+}
+
 pub struct MistyViewModelManager<R> {
     models: Vec<BoxedErasedMistyViewModel<R>>,
+    cache: Mutex<ViewCache<R>>,
 }
 
 #[derive(Default)]
@@ -96,7 +214,7 @@ impl<R> Debug for MistyViewModelManager<R> {
     }
 }
 
-impl<R> MistyViewModelManagerBuilder<R> {
+impl<R: Default> MistyViewModelManagerBuilder<R> {
     pub fn register<'a, S, V>(mut self, view_model: V) -> Self
     where
         S: RefMistyStates + 'static,
@@ -112,6 +230,11 @@ impl<R> MistyViewModelManagerBuilder<R> {
     pub fn build(self) -> MistyViewModelManager<R> {
         MistyViewModelManager {
             models: self.models,
+            cache: Mutex::new(ViewCache {
+                view: Default::default(),
+                draft: Default::default(),
+                seq: 0,
+            }),
         }
     }
 }
@@ -126,15 +249,35 @@ impl<R: Default> MistyViewModelManager<R> {
     }
 }
 
-impl<R: Any + Default + Send + Sync + 'static> ViewNotifier for MistyViewModelManager<R> {
+impl<R: MistyViewTrait> ViewNotifier for MistyViewModelManager<R> {
+    /// Builds the patch against the cached view. Patches and resource actions are numbered
+    /// under the same lock, so hosts can apply them in order.
     fn build_view(&self, inner: &MistyClientInner) -> BoxedView {
-        let mut s = R::default();
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        let cache = &mut *cache;
+        let mut updated = false;
         for model in self.models.iter() {
             if model.inner.should_update(&inner.state_manager) {
-                model.inner.update(&inner.state_manager, &mut s);
+                model.inner.update(&inner.state_manager, &mut cache.draft);
+                updated = true;
             }
         }
 
-        BoxedView::new(s)
+        // the draft equals the cached view after every build, so only changed fields are cloned
+        let patch = if updated {
+            R::diff(&cache.view, &cache.draft)
+        } else {
+            None
+        };
+        if let Some(patch) = patch.as_ref() {
+            cache.view.copy_changed(&cache.draft, patch);
+        }
+        cache.seq += 1;
+
+        BoxedView::new(ViewUpdate {
+            patch,
+            changed_resources: inner.resource_manager.take_all_actions(),
+            seq: cache.seq,
+        })
     }
 }
@@ -0,0 +1,129 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    codecs::{MistyCodec, MistyCodecError},
+    controllers::ControllerRet,
+    registry::MistyDispatchError,
+    resources::ResourceUpdateAction,
+    views::MistyViewTrait,
+};
+
+/// Bumped on every incompatible change of [`MistyWireRet`].
+pub const MISTY_WIRE_VERSION: u16 = 1;
+
+/// A controller result as sent to hosts. `P` is the view patch and `T` the controller value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MistyWireRet<P, T> {
+    pub version: u16,
+    pub result: Result<MistyWireValue<P, T>, MistyWireError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MistyWireValue<P, T> {
+    pub changed_view: Option<P>,
+    pub changed_resources: Vec<ResourceUpdateAction>,
+    pub seq: Option<u64>,
+    pub value: T,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MistyWireErrorKind {
+    UnknownController,
+    Codec,
+    Failed,
+    Rejected,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MistyWireError {
+    pub kind: MistyWireErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum MistyWireDecodeError {
+    Codec(MistyCodecError),
+    UnsupportedVersion(u16),
+}
+
+impl std::fmt::Display for MistyWireDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Codec(err) => write!(f, "{}", err),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported wire version {}", version)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MistyWireDecodeError {}
+
+impl From<MistyCodecError> for MistyWireDecodeError {
+    fn from(err: MistyCodecError) -> Self {
+        Self::Codec(err)
+    }
+}
+
+impl From<&MistyDispatchError> for MistyWireError {
+    fn from(err: &MistyDispatchError) -> Self {
+        let kind = match err {
+            MistyDispatchError::UnknownController(_) => MistyWireErrorKind::UnknownController,
+            MistyDispatchError::Codec(_) => MistyWireErrorKind::Codec,
+            MistyDispatchError::Failed(_) => MistyWireErrorKind::Failed,
+            MistyDispatchError::Rejected(_) => MistyWireErrorKind::Rejected,
+        };
+        Self {
+            kind,
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Only the version, decoded first so results of other versions are reported as such.
+#[derive(Deserialize)]
+struct MistyWireHeader {
+    version: u16,
+}
+
+impl<R, T> From<ControllerRet<R, T>> for MistyWireRet<R::Patch, T>
+where
+    R: MistyViewTrait,
+{
+    fn from(ret: ControllerRet<R, T>) -> Self {
+        Self {
+            version: MISTY_WIRE_VERSION,
+            result: Ok(MistyWireValue {
+                changed_view: ret.changed_view,
+                changed_resources: ret.changed_resources,
+                seq: ret.seq,
+                value: ret.value,
+            }),
+        }
+    }
+}
+
+impl<P, T> MistyWireRet<P, T> {
+    pub fn err(error: MistyWireError) -> Self {
+        Self {
+            version: MISTY_WIRE_VERSION,
+            result: Err(error),
+        }
+    }
+}
+
+impl<P, T> MistyWireRet<P, T>
+where
+    P: DeserializeOwned,
+    T: DeserializeOwned,
+{
+    /// Decodes a result encoded by a registry with the same codec.
+    pub fn decode(codec: &impl MistyCodec, buf: &[u8]) -> Result<Self, MistyWireDecodeError> {
+        let header: MistyWireHeader = codec.decode(buf)?;
+        if header.version != MISTY_WIRE_VERSION {
+            return Err(MistyWireDecodeError::UnsupportedVersion(header.version));
+        }
+        let ret = codec.decode(buf)?;
+        Ok(ret)
+    }
+}